@@ -0,0 +1,109 @@
+use crate::parsers::{colour, ParseError};
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+/// A colour as ASS stores it, `&HAABBGGRR`.
+///
+/// `a` follows ASS: `0x00` is opaque and `0xFF` fully transparent.
+/// `FromStr` accepts `&HAABBGGRR`, `&HBBGGRR&`, decimal integers from SSA v4 and hex
+/// digits without `&H`; when the digits are all decimal they are read as a decimal integer.
+/// `Display` writes the canonical `&HAABBGGRR`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Colour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Colour {
+    pub const WHITE: Self = Self::new(0xFF, 0xFF, 0xFF, 0);
+    pub const BLACK: Self = Self::new(0, 0, 0, 0);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// From the integer value of `&HAABBGGRR`.
+    pub fn from_abgr(abgr: u32) -> Self {
+        let [r, g, b, a] = abgr.to_le_bytes();
+        Self { r, g, b, a }
+    }
+
+    /// The integer value of `&HAABBGGRR`.
+    pub fn to_abgr(self) -> u32 {
+        u32::from_le_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// From conventional RGBA, where alpha `255` is opaque.
+    pub fn from_rgba([r, g, b, alpha]: [u8; 4]) -> Self {
+        Self::new(r, g, b, 255 - alpha)
+    }
+
+    /// To conventional RGBA, where alpha `255` is opaque.
+    pub fn to_rgba(self) -> [u8; 4] {
+        [self.r, self.g, self.b, 255 - self.a]
+    }
+
+    /// Parses `#RRGGBB` or `#RRGGBBAA`, alpha `FF` being opaque.
+    pub fn from_hex(hex: &str) -> std::result::Result<Self, ParseError> {
+        let invalid = || ParseError::invalid_colour(hex, hex, "colour");
+        let digits = hex.strip_prefix('#').ok_or_else(invalid)?;
+        if !matches!(digits.len(), 6 | 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let byte = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid());
+        let alpha = if digits.len() == 8 { byte(6)? } else { 0xFF };
+        Ok(Self::from_rgba([byte(0)?, byte(2)?, byte(4)?, alpha]))
+    }
+
+    /// `#RRGGBBAA`, alpha `FF` being opaque.
+    pub fn to_hex(self) -> String {
+        let [r, g, b, alpha] = self.to_rgba();
+        format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, alpha)
+    }
+}
+
+impl FromStr for Colour {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        colour(s, s, "colour")
+    }
+}
+
+impl Display for Colour {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "&H{:08X}", self.to_abgr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Colour;
+
+    #[test]
+    fn test_colour_forms() {
+        let expected = Colour::new(0x01, 0x00, 0x00, 0x80);
+        assert_eq!("&H80000001".parse::<Colour>().unwrap(), expected);
+        assert_eq!("&h80000001&".parse::<Colour>().unwrap(), expected);
+        assert_eq!("-2147483647".parse::<Colour>().unwrap(), expected);
+        assert_eq!("8000000A".parse::<Colour>().unwrap().r, 0x0A);
+        assert_eq!(
+            "&HFF8000&".parse::<Colour>().unwrap(),
+            Colour::new(0, 0x80, 0xFF, 0)
+        );
+        assert_eq!("16777215".parse::<Colour>().unwrap(), Colour::WHITE);
+        assert!("&Hxyz".parse::<Colour>().is_err());
+        assert_eq!(expected.to_string(), "&H80000001");
+    }
+
+    #[test]
+    fn test_colour_rgba() {
+        let c = Colour::from_hex("#FF800040").unwrap();
+        assert_eq!(c, Colour::new(0xFF, 0x80, 0x00, 0xBF));
+        assert_eq!(c.to_rgba(), [0xFF, 0x80, 0x00, 0x40]);
+        assert_eq!(c.to_hex(), "#FF800040");
+        assert_eq!(Colour::from_hex("#FFFFFF").unwrap(), Colour::WHITE);
+    }
+}
@@ -1,22 +1,155 @@
-use std::fmt::{Debug, Formatter, Result};
+use super::colour::Colour;
+use super::resolved::{state_at, styled_runs, LineState, StyledRun};
+use super::tags::{OverrideItem, Tag, TextSegment};
+use super::timecodes::{FrameTime, Timecodes};
+use super::timestamp::Timestamp;
+use crate::parsers::parse_text;
+use std::fmt::{Debug, Display, Formatter, Result};
+use std::io::{self, Write};
 
 /// Represents an AdvancedSubStation document, consisting of script info, an optional aegisub project garbage, styles, and events.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct SubtitlesFile {
     pub script_info: ScriptInfo,
     pub project_garbage: Option<ProjectGarbage>,
     pub v4styles: Vec<Styles>,
     pub events: Vec<Dialogue>,
+    /// Sections this crate doesn't interpret (`[Fonts]`, `[Graphics]`, `[Aegisub Extradata]`, ...), in file order.
+    pub extra_sections: Vec<ExtraSection>,
 }
 
 impl SubtitlesFile {
     fn _new(fields: Self) -> Self {
         Self { ..fields }
     }
+
+    /// Writes the document as an `.ass` file that `parse_file()` reads back into an identical value.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "\u{feff}{}", self.script_info.print())?;
+
+        if let Some(project_garbage) = &self.project_garbage {
+            write!(writer, "\n{}", project_garbage.print())?;
+        }
+
+        let style_columns = extra_columns(self.v4styles.iter().map(|s| &s.extra_fields));
+        let format: Vec<&str> = STYLE_FORMAT.iter().chain(&style_columns).copied().collect();
+        writeln!(writer, "\n[V4+ Styles]\nFormat: {}", format.join(", "))?;
+        for style in &self.v4styles {
+            writeln!(writer, "{}", style.line(&style_columns))?;
+        }
+
+        // `Text` stays last so that it may contain commas.
+        let event_columns = extra_columns(self.events.iter().map(|e| &e.extra_fields));
+        let (text, columns) = EVENT_FORMAT.split_last().unwrap();
+        let format: Vec<&str> = columns
+            .iter()
+            .chain(&event_columns)
+            .chain([text])
+            .copied()
+            .collect();
+        writeln!(writer, "\n[Events]\nFormat: {}", format.join(", "))?;
+        for event in &self.events {
+            writeln!(writer, "{}", event.line(&event_columns))?;
+        }
+
+        for section in &self.extra_sections {
+            writeln!(writer, "\n[{}]", section.name)?;
+            for line in &section.lines {
+                writeln!(writer, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the document as `.ass` text, see [`SubtitlesFile::write_to`].
+    pub fn to_ass_string(&self) -> String {
+        let mut out = Vec::new();
+        self.write_to(&mut out)
+            .expect("writing to a Vec<u8> cannot fail");
+        String::from_utf8(out).expect("the document is valid UTF-8")
+    }
+}
+
+/// Collects the unknown column names of a styles or events section, in order of first appearance.
+fn extra_columns<'a>(rows: impl Iterator<Item = &'a Vec<(String, String)>>) -> Vec<&'a str> {
+    let mut columns: Vec<&str> = Vec::new();
+    for (column, _) in rows.flatten() {
+        if !columns.contains(&column.as_str()) {
+            columns.push(column);
+        }
+    }
+    columns
+}
+
+/// Looks up the value of an unknown column, empty if the row doesn't have it.
+fn extra_value<'a>(extra_fields: &'a [(String, String)], column: &str) -> &'a str {
+    extra_fields
+        .iter()
+        .find(|(c, _)| c == column)
+        .map_or("", |(_, v)| v)
 }
 
+/// Column order of the `Format:` line written for `[V4+ Styles]`.
+pub(crate) const STYLE_FORMAT: [&str; 23] = [
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "OutlineColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "Underline",
+    "StrikeOut",
+    "ScaleX",
+    "ScaleY",
+    "Spacing",
+    "Angle",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginV",
+    "Encoding",
+];
+
+/// Column order of the `Format:` line written for `[Events]`.
+pub(crate) const EVENT_FORMAT: [&str; 10] = [
+    "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+/// Column order of the `Format:` line of SSA v4's `[V4 Styles]`.
+pub(crate) const SSA_STYLE_FORMAT: [&str; 18] = [
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "TertiaryColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginV",
+    "AlphaLevel",
+    "Encoding",
+];
+
+/// Column order of the `Format:` line of SSA v4's `[Events]`.
+pub(crate) const SSA_EVENT_FORMAT: [&str; 10] = [
+    "Marked", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
 /// `[Script Info]`
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct ScriptInfo {
     pub comments: Vec<String>,
     pub title: String,
@@ -34,39 +167,121 @@ pub struct ScriptInfo {
     pub synch_point: String,
     pub script_updated_by: String,
     pub update_details: String,
+    /// Keys this crate doesn't know (`LayoutResX`, `Kerning`, `Timer`, ...), in file order.
+    pub extra_fields: Vec<(String, String)>,
+    /// The keys in the order they were read, which `print` keeps. Keys missing from it
+    /// come after them, in the order Aegisub writes them.
+    pub key_order: Vec<String>,
 }
 
 impl ScriptInfo {
     pub fn new(fields: Self) -> Self {
         Self { ..fields }
     }
+    /// Returns the `[Script Info]` section, header included.
     pub fn print(&self) -> String {
-        let _ass_comments = self
-            .comments
-            .iter()
-            .map(|comment| format!("; {:#?}\n", comment))
-            .collect::<String>();
+        let mut out = String::from("[Script Info]\n");
+        for comment in &self.comments {
+            out += &format!("; {}\n", comment);
+        }
         let readable_scaled_border_and_shadow = match self.scaled_border_and_shadow {
             true => "yes",
             false => "no",
         };
-        format!(
-            r"Title: {}\nScriptType: {}\nWrapStyle: {:?}\nScaledBorderAndShadow: {}\nYCbCr Matrix: {:?}\nOriginal Script: {:?}\nPlayResX: {}\nPlayResY: {}\nOriginal Translation: {:?}\nOriginal Editing: {:?}\nOriginal Timing: {:?}\nSynch Point: {:?}\nScript Updated By: {:?}\nUpdate Details: {:?}",
-            self.title,
-            self.script_type,
-            self.wrap_style,
-            readable_scaled_border_and_shadow,
-            self.ycbcr_matrix,
-            self.original_script,
-            self.play_res_x,
-            self.play_res_y,
-            self.original_translation,
-            self.original_editing,
-            self.original_timing,
-            self.synch_point,
-            self.script_updated_by,
-            self.update_details,
-        )
+        let readable_ycbcr_matrix = match &self.ycbcr_matrix {
+            Some(ycbcr_matrix) => format!("{:?}", ycbcr_matrix),
+            None => String::from("None"),
+        };
+        let mut fields: Vec<(&str, String)> = vec![
+            ("Title", self.title.clone()),
+            ("ScriptType", self.script_type.clone()),
+            ("WrapStyle", format!("{:?}", self.wrap_style)),
+            (
+                "ScaledBorderAndShadow",
+                readable_scaled_border_and_shadow.to_owned(),
+            ),
+            ("YCbCr Matrix", readable_ycbcr_matrix),
+        ];
+        for (key, value) in [
+            ("Original Script", &self.original_script),
+            ("PlayResX", &non_zero(self.play_res_x)),
+            ("PlayResY", &non_zero(self.play_res_y)),
+            ("Original Translation", &self.original_translation),
+            ("Original Editing", &self.original_editing),
+            ("Original Timing", &self.original_timing),
+            ("Synch Point", &self.synch_point),
+            ("Script Updated By", &self.script_updated_by),
+            ("Update Details", &self.update_details),
+        ] {
+            if !value.is_empty() {
+                fields.push((key, value.clone()));
+            }
+        }
+        fields.extend(
+            self.extra_fields
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.clone())),
+        );
+        for key in &self.key_order {
+            if let Some(i) = fields.iter().position(|(k, _)| k == key) {
+                let (key, value) = fields.remove(i);
+                out += &format!("{}: {}\n", key, value);
+            }
+        }
+        for (key, value) in fields {
+            out += &format!("{}: {}\n", key, value);
+        }
+        out
+    }
+}
+
+/// The order of the keys doesn't change the script, so it isn't compared.
+impl PartialEq for ScriptInfo {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            comments,
+            title,
+            script_type,
+            wrap_style,
+            scaled_border_and_shadow,
+            ycbcr_matrix,
+            original_script,
+            play_res_x,
+            play_res_y,
+            original_translation,
+            original_editing,
+            original_timing,
+            synch_point,
+            script_updated_by,
+            update_details,
+            extra_fields,
+            key_order: _,
+        } = self;
+        *comments == other.comments
+            && *title == other.title
+            && *script_type == other.script_type
+            && *wrap_style == other.wrap_style
+            && *scaled_border_and_shadow == other.scaled_border_and_shadow
+            && *ycbcr_matrix == other.ycbcr_matrix
+            && *original_script == other.original_script
+            && *play_res_x == other.play_res_x
+            && *play_res_y == other.play_res_y
+            && *original_translation == other.original_translation
+            && *original_editing == other.original_editing
+            && *original_timing == other.original_timing
+            && *synch_point == other.synch_point
+            && *script_updated_by == other.script_updated_by
+            && *update_details == other.update_details
+            && *extra_fields == other.extra_fields
+    }
+}
+
+/// Formats a number, or an empty string for `0` so that unset values are left out.
+fn non_zero<T: Display + Default + PartialEq>(value: T) -> String {
+    if value == T::default() {
+        String::new()
+    } else {
+        value.to_string()
     }
 }
 
@@ -88,12 +303,14 @@ impl Default for ScriptInfo {
             synch_point: String::from(""),
             script_updated_by: String::from(""),
             update_details: String::from(""),
+            extra_fields: vec![],
+            key_order: vec![],
         }
     }
 }
 
 /// `[Aegisub Project Garbage]`
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct ProjectGarbage {
     pub last_style_storage: Option<String>,
     pub video_file: Option<String>,
@@ -104,6 +321,8 @@ pub struct ProjectGarbage {
     pub scroll_position: i32,
     pub active_line: i32,
     pub video_position: i32,
+    /// Keys this crate doesn't know, in file order.
+    pub extra_fields: Vec<(String, String)>,
 }
 
 impl ProjectGarbage {
@@ -120,6 +339,7 @@ impl ProjectGarbage {
                 video_position: fields.video_position,
                 active_line: fields.active_line,
                 scroll_position: fields.scroll_position,
+                extra_fields: fields.extra_fields,
             }
         } else {
             Self {
@@ -132,6 +352,7 @@ impl ProjectGarbage {
                 video_ar_mode: fields.video_ar_mode,
                 active_line: fields.active_line,
                 scroll_position: fields.scroll_position,
+                extra_fields: fields.extra_fields,
             }
         }
     }
@@ -142,65 +363,95 @@ impl ProjectGarbage {
         );
         dummy_clip
     }
-    
+
+    /// Returns the `[Aegisub Project Garbage]` section, header included.
     pub fn print(&self) -> String {
-        format!(
-            r#"Last Style Storage: {:?}Audio File: {:?}Video File: {:?}Video AR Mode: {}Video AR Value: {}Video Zoom Percent: {}Scroll Position: {}Active Line: {}Video Position: {}"#,
-            self.last_style_storage,
-            self.audio_file,
-            self.video_file,
-            self.video_ar_mode,
-            self.video_ar_value,
-            self.video_zoom_percent,
-            self.scroll_position,
-            self.active_line,
-            self.video_position
-        )
+        let mut out = String::from("[Aegisub Project Garbage]\n");
+        // Aegisub writes these with six decimals.
+        let decimals = |value: f32| match value {
+            0.0 => String::new(),
+            value => format!("{:.6}", value),
+        };
+        for (key, value) in [
+            (
+                "Last Style Storage",
+                self.last_style_storage.clone().unwrap_or_default(),
+            ),
+            ("Audio File", self.audio_file.clone().unwrap_or_default()),
+            ("Video File", self.video_file.clone().unwrap_or_default()),
+            ("Video AR Mode", non_zero(self.video_ar_mode)),
+            ("Video AR Value", decimals(self.video_ar_value)),
+            ("Video Zoom Percent", decimals(self.video_zoom_percent)),
+            ("Scroll Position", non_zero(self.scroll_position)),
+            ("Active Line", non_zero(self.active_line)),
+            ("Video Position", non_zero(self.video_position)),
+        ] {
+            if !value.is_empty() {
+                out += &format!("{}: {}\n", key, value);
+            }
+        }
+        for (key, value) in &self.extra_fields {
+            out += &format!("{}: {}\n", key, value);
+        }
+        out
     }
 }
 
+/// A section kept verbatim because this crate doesn't interpret it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExtraSection {
+    /// Section name without the brackets, e.g. `Fonts`.
+    pub name: String,
+    /// Lines of the section body, without line endings or trailing blank lines.
+    pub lines: Vec<String>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Styles {
     pub name: String,
     pub font_name: String,
-    pub font_size: i32,
-    pub primary_colour: String,
-    pub secondary_colour: String,
-    pub outline_colour: String,
-    pub back_colour: String,
-    /// `0` for false
-    /// `-1` for true
-    pub bold: i32,
-    /// `0` for false
-    /// `-1` for true
-    pub italic: i32,
-    /// `0` for false
-    /// `-1` for true
-    pub underline: i32,
-    /// `0` for false
-    /// `-1` for true
-    pub strikeout: i32,
-    pub scale_x: i32,
-    pub scale_y: i32,
-    pub spacing: i32,
-    pub angle: i32,
-    pub border_style: i32,
+    pub font_size: f32,
+    pub primary_colour: Colour,
+    pub secondary_colour: Colour,
+    pub outline_colour: Colour,
+    pub back_colour: Colour,
+    /// Written as `-1` for true and `0` for false
+    pub bold: bool,
+    /// Written as `-1` for true and `0` for false
+    pub italic: bool,
+    /// Written as `-1` for true and `0` for false
+    pub underline: bool,
+    /// Written as `-1` for true and `0` for false
+    pub strikeout: bool,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub spacing: f32,
+    pub angle: f32,
+    pub border_style: BorderStyle,
     pub outline: f32,
-    pub shadow: i32,
-    pub alignment: i32,
+    pub shadow: f32,
+    pub alignment: Alignment,
     pub margin_l: f32,
     pub margin_r: f32,
     pub margin_v: f32,
     pub encoding: StyleEncoding,
+    /// Columns of the `Format:` line this crate doesn't know, in their original order.
+    pub extra_fields: Vec<(String, String)>,
 }
 
 impl Styles {
     pub fn new(fields: Self) -> Self {
         Self { ..fields }
     }
+    /// Returns the `Style:` line, with unknown columns after `Encoding`.
     pub fn print(&self) -> String {
-        format!(
-            "Style: {},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:?}",
+        let extra_columns = extra_columns([&self.extra_fields].into_iter());
+        self.line(&extra_columns)
+    }
+
+    fn line(&self, extra_columns: &[&str]) -> String {
+        let mut line = format!(
+            "Style: {},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:?},{},{},{:?},{},{},{},{:?}",
             self.name,
             self.font_name,
             self.font_size,
@@ -208,10 +459,10 @@ impl Styles {
             self.secondary_colour,
             self.outline_colour,
             self.back_colour,
-            self.bold,
-            self.italic,
-            self.underline,
-            self.strikeout,
+            ass_bool(self.bold),
+            ass_bool(self.italic),
+            ass_bool(self.underline),
+            ass_bool(self.strikeout),
             self.scale_x,
             self.scale_y,
             self.spacing,
@@ -224,7 +475,19 @@ impl Styles {
             self.margin_r,
             self.margin_v,
             self.encoding,
-        )
+        );
+        for column in extra_columns {
+            line += ",";
+            line += extra_value(&self.extra_fields, column);
+        }
+        line
+    }
+}
+
+fn ass_bool(value: bool) -> i32 {
+    match value {
+        true => -1,
+        false => 0,
     }
 }
 
@@ -233,27 +496,28 @@ impl Default for Styles {
         Self {
             name: String::from("Default"),
             font_name: String::from("Arial"),
-            font_size: 48,
-            primary_colour: String::from("&H00FFFFFF"),
-            secondary_colour: String::from("&H000000FF"),
-            outline_colour: String::from("&H00000000"),
-            back_colour: String::from("&H00000000"),
-            bold: 0,
-            italic: 0,
-            underline: 0,
-            strikeout: 0,
-            scale_x: 100,
-            scale_y: 100,
-            spacing: 0,
-            angle: 0,
-            border_style: 1,
+            font_size: 48.0,
+            primary_colour: Colour::WHITE,
+            secondary_colour: Colour::new(0xFF, 0, 0, 0),
+            outline_colour: Colour::BLACK,
+            back_colour: Colour::BLACK,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikeout: false,
+            scale_x: 100.0,
+            scale_y: 100.0,
+            spacing: 0.0,
+            angle: 0.0,
+            border_style: BorderStyle::Outline,
             outline: 2.0,
-            shadow: 2,
-            alignment: 2,
+            shadow: 2.0,
+            alignment: Alignment::BottomCenter,
             margin_l: 10.0,
             margin_r: 10.0,
             margin_v: 10.0,
             encoding: StyleEncoding::Default,
+            extra_fields: vec![],
         }
     }
 }
@@ -280,13 +544,17 @@ pub enum StyleEncoding {
     Thai,
     EastEuropean,
     Oem,
+    Symbol,
+    /// Any other charset number, kept as is.
+    Other(i32),
 }
 
 impl Debug for StyleEncoding {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
-            Self::Ansi => write!(f, "{}", 1),
-            Self::Default => write!(f, "{}", 2),
+            Self::Ansi => write!(f, "{}", 0),
+            Self::Default => write!(f, "{}", 1),
+            Self::Symbol => write!(f, "{}", 2),
             Self::Mac => write!(f, "{}", 77),
             Self::ShiftJis => write!(f, "{}", 128),
             Self::Hangeul => write!(f, "{}", 129),
@@ -303,15 +571,125 @@ impl Debug for StyleEncoding {
             Self::Thai => write!(f, "{}", 222),
             Self::EastEuropean => write!(f, "{}", 238),
             Self::Oem => write!(f, "{}", 255),
+            Self::Other(encoding) => write!(f, "{}", encoding),
         }
     }
 }
+#[allow(clippy::derivable_impls)]
 impl Default for StyleEncoding {
     fn default() -> Self {
         Self::Default
     }
 }
 
+/// Position of a line on screen, laid out like a numpad.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Alignment {
+    /// `1`
+    BottomLeft,
+    /// `2`
+    #[default]
+    BottomCenter,
+    /// `3`
+    BottomRight,
+    /// `4`
+    MiddleLeft,
+    /// `5`
+    MiddleCenter,
+    /// `6`
+    MiddleRight,
+    /// `7`
+    TopLeft,
+    /// `8`
+    TopCenter,
+    /// `9`
+    TopRight,
+}
+
+impl Alignment {
+    /// From the numpad number used by `Alignment` and `\an`.
+    pub fn from_numpad(numpad: i32) -> Option<Self> {
+        match numpad {
+            1 => Some(Self::BottomLeft),
+            2 => Some(Self::BottomCenter),
+            3 => Some(Self::BottomRight),
+            4 => Some(Self::MiddleLeft),
+            5 => Some(Self::MiddleCenter),
+            6 => Some(Self::MiddleRight),
+            7 => Some(Self::TopLeft),
+            8 => Some(Self::TopCenter),
+            9 => Some(Self::TopRight),
+            _ => None,
+        }
+    }
+
+    pub fn numpad(self) -> i32 {
+        match self {
+            Self::BottomLeft => 1,
+            Self::BottomCenter => 2,
+            Self::BottomRight => 3,
+            Self::MiddleLeft => 4,
+            Self::MiddleCenter => 5,
+            Self::MiddleRight => 6,
+            Self::TopLeft => 7,
+            Self::TopCenter => 8,
+            Self::TopRight => 9,
+        }
+    }
+
+    /// From the SSA v4 numbering used by `\a`: 1 to 3 at the bottom, add 4 for the top
+    /// and 8 for the middle.
+    pub fn from_legacy(legacy: i32) -> Option<Self> {
+        let row = match legacy {
+            1..=3 => 0,
+            5..=7 => 6,
+            9..=11 => 3,
+            _ => return None,
+        };
+        Self::from_numpad(row + (legacy & 3))
+    }
+
+    pub fn legacy(self) -> i32 {
+        let numpad = self.numpad();
+        let row = match numpad {
+            1..=3 => 0,
+            4..=6 => 8,
+            _ => 4,
+        };
+        row + (numpad - 1) % 3 + 1
+    }
+}
+
+impl Debug for Alignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.numpad())
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BorderStyle {
+    /// `1`: Outline and drop shadow
+    #[default]
+    Outline,
+    /// `3`: Opaque box behind each line, coloured with the outline colour
+    OpaqueBox,
+    /// `4`: libass extension, one box behind the whole event, coloured with the back colour
+    BackgroundBox,
+    /// Any other number, kept as is and drawn as an outline
+    Other(i32),
+}
+
+impl Debug for BorderStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Outline => write!(f, "{}", 1),
+            Self::OpaqueBox => write!(f, "{}", 3),
+            Self::BackgroundBox => write!(f, "{}", 4),
+            Self::Other(border_style) => write!(f, "{}", border_style),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum EventType {
     Dialogue,
@@ -319,12 +697,12 @@ pub enum EventType {
 }
 
 /// `[Events]`
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Dialogue {
     pub type_: EventType,
     pub layer: i64,
-    pub start: String,
-    pub end: String,
+    pub start: Timestamp,
+    pub end: Timestamp,
     pub style: String,
     pub name: String,
     pub margin_l: f64,
@@ -332,15 +710,86 @@ pub struct Dialogue {
     pub margin_v: f64,
     pub effect: String,
     pub text: String,
+    /// Columns of the `Format:` line this crate doesn't know, in their original order.
+    pub extra_fields: Vec<(String, String)>,
 }
 
 impl Dialogue {
     pub fn new(fields: Self) -> Self {
         Self { ..fields }
     }
+    /// Returns the `Dialogue:` or `Comment:` line, with unknown columns before `Text`.
     pub fn print(&self) -> String {
-        format!(
-            "{:?}: {},{},{},{},{},{},{},{},{},{}",
+        let extra_columns = extra_columns([&self.extra_fields].into_iter());
+        self.line(&extra_columns)
+    }
+
+    /// Splits `text` into text, line breaks and decoded override blocks, as leniently
+    /// as libass reads them.
+    pub fn parse_text(&self) -> Vec<TextSegment> {
+        parse_text(&self.text)
+    }
+
+    /// The text without override blocks or drawings: `\N` becomes a newline, `\h` a
+    /// non-breaking space and `\n` a newline only under wrap style 2, a space otherwise.
+    /// A `\q` in the line takes precedence over `wrap_style`, the script's `WrapStyle`.
+    pub fn plain_text(&self, wrap_style: &WrapStyle) -> String {
+        let segments = self.parse_text();
+        let line_wrap_style = segments
+            .iter()
+            .filter_map(|segment| match segment {
+                TextSegment::Override(items) => Some(items),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                OverrideItem::Tag(Tag::WrapStyle(q)) => Some(q.as_ref().unwrap_or(wrap_style)),
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or(wrap_style);
+
+        let mut text = String::new();
+        for segment in &segments {
+            match segment {
+                TextSegment::Text(run) => text.push_str(run),
+                TextSegment::HardBreak => text.push('\n'),
+                TextSegment::SoftBreak if *line_wrap_style == WrapStyle::WrapStyle2 => {
+                    text.push('\n')
+                }
+                TextSegment::SoftBreak => text.push(' '),
+                TextSegment::HardSpace => text.push('\u{a0}'),
+                TextSegment::Override(_) | TextSegment::Drawing(_) => {}
+            }
+        }
+        text
+    }
+
+    /// Every segment of the text but the override blocks, with the style it is drawn in.
+    /// `styles` are the script's styles, needed for the line's style and `\rStyle`.
+    pub fn styled_runs(&self, styles: &[Styles]) -> Vec<StyledRun> {
+        styled_runs(self, styles)
+    }
+
+    /// The line as it looks at `time`, with `\t`, `\move`, `\fad`, `\fade` and karaoke
+    /// evaluated. `time` is clamped to the line's `start`..`end`.
+    pub fn state_at(&self, styles: &[Styles], time: Timestamp) -> LineState {
+        state_at(self, styles, time)
+    }
+
+    /// The first frame the line is shown on.
+    pub fn start_frame(&self, timecodes: &Timecodes) -> i64 {
+        timecodes.frame_at(self.start.as_millis() as i64, FrameTime::Start)
+    }
+
+    /// The last frame the line is shown on, before the start frame if it is on none.
+    pub fn end_frame(&self, timecodes: &Timecodes) -> i64 {
+        timecodes.frame_at(self.end.as_millis() as i64, FrameTime::End)
+    }
+
+    fn line(&self, extra_columns: &[&str]) -> String {
+        let mut line = format!(
+            "{:?}: {},{},{},{},{},{},{},{},{},",
             self.type_,
             self.layer,
             self.start,
@@ -351,8 +800,12 @@ impl Dialogue {
             self.margin_r,
             self.margin_v,
             self.effect,
-            self.text
-        )
+        );
+        for column in extra_columns {
+            line += extra_value(&self.extra_fields, column);
+            line += ",";
+        }
+        line + &self.text
     }
 }
 impl Default for Dialogue {
@@ -360,8 +813,8 @@ impl Default for Dialogue {
         Self {
             type_: EventType::Dialogue,
             layer: 0,
-            start: String::from("0:00:00.00"),
-            end: String::from("0:00:05.00"),
+            start: Timestamp::ZERO,
+            end: Timestamp::new(0, 0, 5, 0),
             style: String::from("Default"),
             name: "".to_string(),
             margin_l: 0.0,
@@ -369,6 +822,7 @@ impl Default for Dialogue {
             margin_v: 0.0,
             effect: "".to_string(),
             text: "".to_string(),
+            extra_fields: vec![],
         }
     }
 }
@@ -395,6 +849,7 @@ impl Debug for WrapStyle {
         }
     }
 }
+#[allow(clippy::derivable_impls)]
 impl Default for WrapStyle {
     fn default() -> Self {
         Self::WrapStyle0
@@ -411,6 +866,8 @@ pub enum YcbcrMatrix {
     Pcfcc,
     Tv240m,
     Pc240m,
+    /// Any other matrix name, kept as is.
+    Other(String),
 }
 
 impl Debug for YcbcrMatrix {
@@ -424,59 +881,36 @@ impl Debug for YcbcrMatrix {
             Self::Pcfcc => write!(f, "PC.FCC"),
             Self::Tv240m => write!(f, "TV.240M"),
             Self::Pc240m => write!(f, "PC.240M"),
+            Self::Other(matrix) => write!(f, "{}", matrix),
         }
     }
 }
 
-#[cfg(unstable)]
-pub trait StyleTags {
-    fn fn_(font_name: f64) -> String {
-        format!("fn({})", font_name)
-    }
-    fn fs(font_size: f64) -> String {
-        format!("fs({})", font_size)
-    }
-    fn i(italisize: bool, _text: &str) -> String {
-        if italisize == false {
-            format!(r#"{{i{italisize}}}{}"#, 0)
-        } else {
-            format!(r#"{{i{italisize}}}{}"#, 1)
-        }
-    }
-    fn p1(vector_drawing: &str) -> String {
-        format!("q1({})", vector_drawing)
-    }
-    fn blur(blur_value: f64) -> String {
-        format!("q1({})", blur_value)
-    }
-    fn an(angle: i8) -> String {
-        if angle > 9 {
-            format!("an{}", 9)
-        } else if angle < 1 {
-            format!("an{}", 1)
-        } else {
-            format!("an{}", angle)
-        }
-    }
-    fn b(is_bold: bool) -> String {
-        match is_bold {
-            true => format!("b{}", 1),
-            false => format!("b{}", 0),
-        }
-    }
-    fn c(c: i8, color: i32) -> String {
-        match c {
-            1 => format!("1c&{}", color),
-            2 => format!("2c&{}", color),
-            3 => format!("3c&{}", color),
-            i8::MIN..=0_i8 => format!("c&{}", color),
-            4_i8..=i8::MAX => unimplemented!(),
-        }
-    }
-    fn pos(x: f32, y: f32) -> String {
-        format!("pos({},{})", x, y)
-    }
-    fn q2() -> String {
-        "q2".to_string()
+#[cfg(test)]
+mod tests {
+    use super::{Dialogue, WrapStyle};
+
+    #[test]
+    fn test_plain_text() {
+        let dialogue = Dialogue {
+            text: r"{\an8}Hello\Nsmall\hworld{\p1}m 0 0 l 1 1{\p0}\n!".to_owned(),
+            ..Dialogue::default()
+        };
+        assert_eq!(
+            dialogue.plain_text(&WrapStyle::WrapStyle0),
+            "Hello\nsmall\u{a0}world !"
+        );
+        assert_eq!(
+            dialogue.plain_text(&WrapStyle::WrapStyle2),
+            "Hello\nsmall\u{a0}world\n!"
+        );
+        let dialogue = Dialogue {
+            text: format!("{{\\q2}}{}", dialogue.text),
+            ..Dialogue::default()
+        };
+        assert_eq!(
+            dialogue.plain_text(&WrapStyle::WrapStyle0),
+            "Hello\nsmall\u{a0}world\n!"
+        );
     }
 }
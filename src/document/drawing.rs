@@ -0,0 +1,179 @@
+use crate::parsers::drawing;
+use std::fmt::{Display, Formatter, Result};
+
+/// A point of a drawing, in drawing coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// A command of a drawing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawingCommand {
+    /// `m`: closes the current shape and starts a new one
+    Move(Point),
+    /// `n`: moves without closing the current shape
+    MoveNoClose(Point),
+    /// `l`: lines through every point
+    Line(Vec<Point>),
+    /// `b`: cubic Bézier curves, three points each
+    Bezier(Vec<Point>),
+    /// `s`: a cubic B-spline through at least three points
+    Spline(Vec<Point>),
+    /// `p`: extends the last B-spline
+    ExtendSpline(Vec<Point>),
+    /// `c`: closes the last B-spline
+    CloseSpline,
+}
+
+/// The vector drawing of `\p1` mode or of a vector `\clip`, e.g. `m 0 0 l 100 0 100 100`.
+///
+/// `parse` is as lenient as libass: anything but commands and numbers is skipped, as is a
+/// coordinate missing its pair. `Display` writes the commands back, separated by spaces.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Drawing {
+    pub commands: Vec<DrawingCommand>,
+}
+
+impl Drawing {
+    pub fn parse(s: &str) -> Self {
+        drawing(s)
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &Point> {
+        self.commands.iter().flat_map(|command| match command {
+            DrawingCommand::Move(p) | DrawingCommand::MoveNoClose(p) => std::slice::from_ref(p),
+            DrawingCommand::Line(points)
+            | DrawingCommand::Bezier(points)
+            | DrawingCommand::Spline(points)
+            | DrawingCommand::ExtendSpline(points) => points.as_slice(),
+            DrawingCommand::CloseSpline => &[],
+        })
+    }
+
+    pub fn points_mut(&mut self) -> impl Iterator<Item = &mut Point> {
+        self.commands.iter_mut().flat_map(|command| match command {
+            DrawingCommand::Move(p) | DrawingCommand::MoveNoClose(p) => std::slice::from_mut(p),
+            DrawingCommand::Line(points)
+            | DrawingCommand::Bezier(points)
+            | DrawingCommand::Spline(points)
+            | DrawingCommand::ExtendSpline(points) => points.as_mut_slice(),
+            DrawingCommand::CloseSpline => &mut [],
+        })
+    }
+
+    /// The smallest box holding every point, as `(top left, bottom right)`. Curves never
+    /// leave the box of their control points, though they may not reach its edges.
+    pub fn bounding_box(&self) -> Option<(Point, Point)> {
+        self.points().fold(None, |bbox, p| {
+            let (min, max) = bbox.unwrap_or((*p, *p));
+            Some((
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            ))
+        })
+    }
+
+    /// Applies `f` to every point.
+    pub fn transform(&mut self, f: impl Fn(Point) -> Point) {
+        for p in self.points_mut() {
+            *p = f(*p);
+        }
+    }
+
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        self.transform(|p| Point::new(p.x + dx, p.y + dy));
+    }
+
+    pub fn scale(&mut self, sx: f64, sy: f64) {
+        self.transform(|p| Point::new(p.x * sx, p.y * sy));
+    }
+
+    /// Rotates by `degrees` around `origin`, counter-clockwise on screen like `\frz`.
+    pub fn rotate(&mut self, degrees: f64, origin: Point) {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        self.transform(|p| {
+            let (dx, dy) = (p.x - origin.x, p.y - origin.y);
+            Point::new(
+                origin.x + dx * cos + dy * sin,
+                origin.y - dx * sin + dy * cos,
+            )
+        });
+    }
+
+    /// Divides the coordinates by 2^(`scale` - 1), the meaning of `\p<scale>` and of the
+    /// scale of `\clip(scale,drawing)`, so they are in script pixels.
+    pub fn apply_scale(&mut self, scale: u32) {
+        let factor = 0.5f64.powi(scale.max(1) as i32 - 1);
+        self.scale(factor, factor);
+    }
+}
+
+impl Display for Drawing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut separator = "";
+        for command in &self.commands {
+            let (name, points) = match command {
+                DrawingCommand::Move(p) => ("m", std::slice::from_ref(p)),
+                DrawingCommand::MoveNoClose(p) => ("n", std::slice::from_ref(p)),
+                DrawingCommand::Line(points) => ("l", points.as_slice()),
+                DrawingCommand::Bezier(points) => ("b", points.as_slice()),
+                DrawingCommand::Spline(points) => ("s", points.as_slice()),
+                DrawingCommand::ExtendSpline(points) => ("p", points.as_slice()),
+                DrawingCommand::CloseSpline => ("c", &[][..]),
+            };
+            write!(f, "{}{}", separator, name)?;
+            for p in points {
+                write!(f, " {} {}", p.x, p.y)?;
+            }
+            separator = " ";
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Drawing, DrawingCommand, Point};
+
+    #[test]
+    fn test_drawing_parse_and_write() {
+        let drawing =
+            Drawing::parse("m 0 0 l 10 0 10 10 b 0 10 -5 5 0 0.5 s 1 1 2 2 3 3 p 4 4 c n 7 ");
+        assert_eq!(
+            drawing.commands[..2],
+            [
+                DrawingCommand::Move(Point::new(0.0, 0.0)),
+                DrawingCommand::Line(vec![Point::new(10.0, 0.0), Point::new(10.0, 10.0)]),
+            ]
+        );
+        assert_eq!(drawing.commands.len(), 6);
+        assert_eq!(
+            drawing.to_string(),
+            "m 0 0 l 10 0 10 10 b 0 10 -5 5 0 0.5 s 1 1 2 2 3 3 p 4 4 c"
+        );
+        assert_eq!(
+            drawing.bounding_box(),
+            Some((Point::new(-5.0, 0.0), Point::new(10.0, 10.0)))
+        );
+    }
+
+    #[test]
+    fn test_drawing_transforms() {
+        let mut drawing = Drawing::parse("m 0 0 l 20 0 20 10");
+        drawing.apply_scale(2);
+        drawing.translate(1.0, 1.0);
+        assert_eq!(drawing.to_string(), "m 1 1 l 11 1 11 6");
+        drawing.rotate(90.0, Point::new(1.0, 1.0));
+        let (min, max) = drawing.bounding_box().unwrap();
+        assert!((min.x - 1.0).abs() < 1e-9 && (min.y + 9.0).abs() < 1e-9);
+        assert!((max.x - 6.0).abs() < 1e-9 && (max.y - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::parsers::{parse_keyframes, ParseError};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// The keyframes of a video, by frame number.
+///
+/// `FromStr` accepts the files Aegisub loads: its own keyframe format v1, XviD and SCXviD
+/// 2-pass stats and x264 stats.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Keyframes {
+    /// In increasing order, without duplicates.
+    pub frames: Vec<i64>,
+}
+
+impl Keyframes {
+    /// The keyframe nearest to `frame`, the earlier one of two as near.
+    pub fn closest(&self, frame: i64) -> Option<i64> {
+        self.closest_within(frame, i64::MIN..=i64::MAX)
+    }
+
+    /// The keyframe in `range` nearest to `frame`, the earlier one of two as near.
+    pub(crate) fn closest_within(&self, frame: i64, range: RangeInclusive<i64>) -> Option<i64> {
+        let next = self.frames.partition_point(|&key| key < frame);
+        let before = next
+            .checked_sub(1)
+            .map(|i| self.frames[i])
+            .filter(|key| range.contains(key));
+        let after = self.frames.get(next).filter(|key| range.contains(key));
+        match (before, after) {
+            (Some(before), Some(&after)) if after - frame < frame - before => Some(after),
+            (None, after) => after.copied(),
+            (before, _) => before,
+        }
+    }
+}
+
+impl FromStr for Keyframes {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_keyframes(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keyframes;
+    use crate::prelude::{
+        Dialogue, EventFilter, KeyframeThresholds, ParseError, SubtitlesFile, Timecodes, Timestamp,
+    };
+
+    #[test]
+    fn test_keyframes() {
+        let aegisub: Keyframes = "# keyframe format v1\nfps 0\n0\n48\n24\n".parse().unwrap();
+        assert_eq!(aegisub.frames, [0, 24, 48]);
+        let xvid: Keyframes = "# XviD 2pass stat file\n# frames\ni 1 2\np 3 4\nb 5 6\nI 7 8\ni 9 10\n"
+            .parse()
+            .unwrap();
+        assert_eq!(xvid.frames, [0, 3]);
+        let x264: Keyframes = "#options: 1280x720 fps=24000/1001\n\
+            in:0 out:0 type:I dur:2\nin:2 out:1 type:P dur:2\nin:1 out:2 type:B dur:2\n\
+            in:3 out:3 type:i dur:2\n"
+            .parse()
+            .unwrap();
+        assert_eq!(x264.frames, [0, 3]);
+        assert!(matches!(
+            "# keyframe format v1\nfps 0\n12a".parse::<Keyframes>(),
+            Err(ParseError::InvalidNumber { line: 3, .. })
+        ));
+        assert_eq!(aegisub.closest(12), Some(0));
+        assert_eq!(aegisub.closest(13), Some(24));
+        assert_eq!(aegisub.closest(100), Some(48));
+
+        let timecodes = Timecodes::constant(25.0);
+        let event = |start, end| Dialogue {
+            start: Timestamp::from_millis(start),
+            end: Timestamp::from_millis(end),
+            ..Dialogue::default()
+        };
+        let mut file = SubtitlesFile {
+            // Frames 22 to 45, 26 to 49 and 30 to 40.
+            events: vec![event(880, 1840), event(1040, 2000), event(1200, 1640)],
+            ..SubtitlesFile::default()
+        };
+        let thresholds = KeyframeThresholds {
+            starts_before: 2,
+            starts_after: 2,
+            ends_before: 2,
+            ends_after: 2,
+        };
+        file.snap_to_keyframes(&aegisub, &timecodes, &thresholds, &EventFilter::default());
+        assert_eq!(file.events[0].start, Timestamp::from_millis(940));
+        assert_eq!(file.events[0].end, Timestamp::from_millis(1900));
+        assert_eq!(file.events[1].start, Timestamp::from_millis(940));
+        assert_eq!(file.events[1].end, Timestamp::from_millis(1900));
+        assert_eq!(file.events[2].start, Timestamp::from_millis(1200));
+        assert_eq!(file.events[2].end, Timestamp::from_millis(1640));
+
+        // Frame 12 is as near to 0 as to 24, but only 24 is within the thresholds.
+        let mut file = SubtitlesFile {
+            events: vec![event(480, 1000)],
+            ..SubtitlesFile::default()
+        };
+        let thresholds = KeyframeThresholds {
+            starts_before: 12,
+            starts_after: 0,
+            ends_before: 0,
+            ends_after: 0,
+        };
+        file.snap_to_keyframes(&aegisub, &timecodes, &thresholds, &EventFilter::default());
+        assert_eq!(file.events[0].start, Timestamp::from_millis(940));
+    }
+}
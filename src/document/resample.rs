@@ -0,0 +1,278 @@
+use super::document::{EventType, SubtitlesFile};
+use super::drawing::{Drawing, Point};
+use super::tags::{write_text, ClipShape, OverrideItem, Tag, TextSegment, TextWriteOptions};
+
+/// How [`SubtitlesFile::resample`] handles a change of aspect ratio. Ratios within 1% of
+/// each other, such as 848x480 and 1280x720, are left as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AspectMode {
+    /// Stretches everything horizontally, with `ScaleX` and `\fscx` for the text and `\p`
+    /// drawings.
+    #[default]
+    Stretch,
+    /// Keeps the width of the picture, adding bars above and below or cropping there.
+    /// Aegisub's "Add borders" when going taller, "Remove borders" when going wider.
+    Letterbox,
+    /// Keeps the height of the picture, adding bars left and right or cropping there.
+    /// Aegisub's "Add borders" when going wider, "Remove borders" when going taller.
+    Pillarbox,
+}
+
+/// What resampling does to a coordinate or a size.
+struct Resample {
+    /// Added to x and y coordinates before scaling, the bars of letterboxing or
+    /// pillarboxing.
+    offset_x: f64,
+    offset_y: f64,
+    rx: f64,
+    ry: f64,
+    /// The factor of `ScaleX` and `\fscx` when stretching.
+    stretch: f64,
+}
+
+impl Resample {
+    fn x(&self, x: f64) -> f64 {
+        (x + self.offset_x) * self.rx
+    }
+
+    fn y(&self, y: f64) -> f64 {
+        (y + self.offset_y) * self.ry
+    }
+
+    /// `\p` drawings are placed by the line, so only scaled, and widened by `\fscx` when
+    /// stretching, like the text; clips are moved too.
+    fn drawing(&self, drawing: &mut Drawing, scale: u32, placed: bool) {
+        let factor = 2f64.powi(scale.max(1) as i32 - 1);
+        let (dx, dy, rx) = match placed {
+            true => (0.0, 0.0, self.rx / self.stretch),
+            false => (self.offset_x * factor, self.offset_y * factor, self.rx),
+        };
+        let round = |n: f64| (n * 1000.0).round() / 1000.0;
+        drawing.transform(|p| Point::new(round((p.x + dx) * rx), round((p.y + dy) * self.ry)));
+    }
+
+    /// Rescales the numbers of `tag`, returning whether there were any.
+    fn tag(&self, tag: &mut Tag) -> bool {
+        let scale = |value: &mut Option<f64>, factor: f64| {
+            if let Some(v) = value {
+                *v *= factor;
+            }
+        };
+        match tag {
+            Tag::FontSize(v)
+            | Tag::Border(v)
+            | Tag::BorderY(v)
+            | Tag::Shadow(v)
+            | Tag::ShadowY(v)
+            | Tag::Blur(v)
+            | Tag::BaselineOffset(v) => scale(v, self.ry),
+            Tag::BorderX(v) | Tag::ShadowX(v) | Tag::Spacing(v) => scale(v, self.rx),
+            Tag::ScaleX(v) => scale(v, self.stretch),
+            Tag::Position { x, y } | Tag::Origin { x, y } => {
+                (*x, *y) = (self.x(*x), self.y(*y));
+            }
+            Tag::Move { x1, y1, x2, y2, .. } => {
+                (*x1, *y1) = (self.x(*x1), self.y(*y1));
+                (*x2, *y2) = (self.x(*x2), self.y(*y2));
+            }
+            Tag::Clip {
+                shape: ClipShape::Rectangle { x1, y1, x2, y2 },
+                ..
+            } => {
+                (*x1, *y1) = (self.x(*x1), self.y(*y1));
+                (*x2, *y2) = (self.x(*x2), self.y(*y2));
+            }
+            Tag::Clip {
+                shape: ClipShape::Vector { scale, drawing },
+                ..
+            } => self.drawing(drawing, scale.unwrap_or(1), false),
+            Tag::Transform { tags, .. } => {
+                return tags
+                    .iter_mut()
+                    .fold(false, |changed, tag| self.tag(tag) | changed)
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// The resolution renderers assume when `PlayResX` or `PlayResY` is missing.
+pub(crate) fn play_res(x: i32, y: i32) -> (i32, i32) {
+    match (x, y) {
+        (0, 0) => (384, 288),
+        (0, 1024) => (1280, 1024),
+        (0, y) => (y * 4 / 3, y),
+        (1280, 0) => (1280, 1024),
+        (x, 0) => (x, x * 3 / 4),
+        (x, y) => (x, y),
+    }
+}
+
+impl SubtitlesFile {
+    /// Changes `PlayResX` and `PlayResY` to `new_x` by `new_y`, rescaling the styles' font
+    /// sizes, outlines, shadows, spacings and margins, the events' margins and the
+    /// positions, sizes, clips and drawings of override tags, like Aegisub's "Resample
+    /// Resolution". Karaoke templates are left alone.
+    pub fn resample(&mut self, new_x: i32, new_y: i32, aspect_mode: AspectMode) {
+        let (old_x, old_y) = play_res(self.script_info.play_res_x, self.script_info.play_res_y);
+        let (old_x, old_y, new_x_f, new_y_f) = (
+            f64::from(old_x),
+            f64::from(old_y),
+            f64::from(new_x),
+            f64::from(new_y),
+        );
+        let (old_ar, new_ar) = (old_x / old_y, new_x_f / new_y_f);
+        let (mut offset_x, mut offset_y, mut stretch) = (0.0, 0.0, 1.0);
+        if (old_ar - new_ar).abs() / new_ar > 0.01 {
+            match aspect_mode {
+                AspectMode::Stretch => stretch = new_ar / old_ar,
+                AspectMode::Letterbox => offset_y = ((old_x / new_ar - old_y) / 2.0).trunc(),
+                AspectMode::Pillarbox => offset_x = ((old_y * new_ar - old_x) / 2.0).trunc(),
+            }
+        }
+        let resample = Resample {
+            offset_x,
+            offset_y,
+            rx: new_x_f / (old_x + 2.0 * offset_x),
+            ry: new_y_f / (old_y + 2.0 * offset_y),
+            stretch,
+        };
+        let (rx, ry) = (resample.rx as f32, resample.ry as f32);
+        let margin = |m: f32, offset: f64, r: f32| ((m + offset as f32) * r).round();
+
+        for style in &mut self.v4styles {
+            style.font_size = (style.font_size * ry).round();
+            style.outline *= ry;
+            style.shadow *= ry;
+            style.spacing *= rx;
+            style.scale_x *= stretch as f32;
+            style.margin_l = margin(style.margin_l, offset_x, rx);
+            style.margin_r = margin(style.margin_r, offset_x, rx);
+            style.margin_v = margin(style.margin_v, offset_y, ry);
+        }
+
+        for event in &mut self.events {
+            if event.type_ == EventType::Comment
+                && (event.effect.starts_with("template") || event.effect.starts_with("code"))
+            {
+                continue;
+            }
+            let margin = |m: f64, offset: f64, r: f64| match m {
+                0.0 => 0.0,
+                m => ((m + offset) * r).round(),
+            };
+            event.margin_l = margin(event.margin_l, offset_x, resample.rx);
+            event.margin_r = margin(event.margin_r, offset_x, resample.rx);
+            event.margin_v = margin(event.margin_v, offset_y, resample.ry);
+
+            let mut segments = event.parse_text();
+            let mut changed = false;
+            let mut drawing_scale = 0;
+            for segment in &mut segments {
+                match segment {
+                    TextSegment::Override(items) => {
+                        for item in items {
+                            if let OverrideItem::Tag(tag) = item {
+                                if let Tag::DrawingMode(scale) = tag {
+                                    drawing_scale = *scale;
+                                }
+                                changed |= resample.tag(tag);
+                            }
+                        }
+                    }
+                    TextSegment::Drawing(drawing) => {
+                        resample.drawing(drawing, drawing_scale, true);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if changed {
+                let options = TextWriteOptions {
+                    precision: Some(3),
+                    ..TextWriteOptions::default()
+                };
+                event.text = write_text(&segments, &options);
+            }
+        }
+
+        self.script_info.play_res_x = new_x;
+        self.script_info.play_res_y = new_y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AspectMode;
+    use crate::prelude::{Dialogue, ScriptInfo, Styles, SubtitlesFile};
+
+    fn file(text: &str) -> SubtitlesFile {
+        SubtitlesFile {
+            script_info: ScriptInfo {
+                play_res_x: 640,
+                play_res_y: 480,
+                ..ScriptInfo::default()
+            },
+            v4styles: vec![Styles {
+                font_size: 40.0,
+                outline: 2.0,
+                margin_l: 10.0,
+                margin_v: 20.0,
+                ..Styles::default()
+            }],
+            events: vec![Dialogue {
+                margin_l: 30.0,
+                text: text.to_owned(),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        }
+    }
+
+    #[test]
+    fn test_resample() {
+        let text = r"{\pos(320,240)\fs20\bord1\t(\xbord2)\clip(m 0 0 l 64 48)\p1}m 0 0 l 10 10";
+        let mut same_ar = file(text);
+        same_ar.resample(1280, 960, AspectMode::Stretch);
+        assert_eq!(
+            (
+                same_ar.script_info.play_res_x,
+                same_ar.script_info.play_res_y
+            ),
+            (1280, 960)
+        );
+        let style = &same_ar.v4styles[0];
+        assert_eq!(
+            (
+                style.font_size,
+                style.outline,
+                style.margin_l,
+                style.margin_v
+            ),
+            (80.0, 4.0, 20.0, 40.0)
+        );
+        assert_eq!(same_ar.events[0].margin_l, 60.0);
+        assert_eq!(
+            same_ar.events[0].text,
+            r"{\pos(640,480)\fs40\bord2\t(\xbord4)\clip(m 0 0 l 128 96)\p1}m 0 0 l 20 20"
+        );
+
+        let mut stretched = file(r"{\pos(320,240)\fscx100\clip(m 0 0 l 64 48)\p1}m 0 0 l 20 20");
+        stretched.resample(1280, 720, AspectMode::Stretch);
+        assert!((stretched.v4styles[0].scale_x - 133.333).abs() < 1e-3);
+        assert_eq!(stretched.v4styles[0].font_size, 60.0);
+        // `\fscx` widens the drawing the rest of the way to 40.
+        assert_eq!(
+            stretched.events[0].text,
+            r"{\pos(640,360)\fscx133.333\clip(m 0 0 l 128 72)\p1}m 0 0 l 30 30"
+        );
+
+        // 640x480 in the middle of 852x480, then scaled to 1280x720.
+        let mut pillarboxed = file(r"{\pos(0,240)}a");
+        pillarboxed.resample(1280, 720, AspectMode::Pillarbox);
+        assert_eq!(pillarboxed.v4styles[0].scale_x, 100.0);
+        assert_eq!(pillarboxed.v4styles[0].margin_l, 174.0);
+        assert_eq!(pillarboxed.events[0].text, r"{\pos(159.249,360)}a");
+    }
+}
@@ -0,0 +1,608 @@
+use super::colour::Colour;
+use super::document::{Alignment, BorderStyle, Dialogue, Styles};
+use super::tags::{ClipShape, KaraokeEffect, OverrideItem, Tag, TextSegment};
+use super::timestamp::Timestamp;
+
+/// What a run of text looks like once its style and the override tags before it are
+/// applied, see [`Dialogue::styled_runs`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedStyle {
+    /// The style in effect, the line's or the last `\r` one.
+    pub style: String,
+    pub font_name: String,
+    pub font_size: f64,
+    /// Colours with their alphas, `a` being 0 for opaque as in ASS.
+    pub primary_colour: Colour,
+    pub secondary_colour: Colour,
+    pub outline_colour: Colour,
+    pub back_colour: Colour,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikeout: bool,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub spacing: f64,
+    pub rotation_x: f64,
+    pub rotation_y: f64,
+    pub rotation_z: f64,
+    pub shear_x: f64,
+    pub shear_y: f64,
+    pub border_style: BorderStyle,
+    pub border_x: f64,
+    pub border_y: f64,
+    pub shadow_x: f64,
+    pub shadow_y: f64,
+    pub blur_edges: f64,
+    pub blur: f64,
+    /// The first `\an` or `\a` of the line, which applies to all of it.
+    pub alignment: Alignment,
+    /// The line's margins where they aren't 0, the style's otherwise.
+    pub margin_l: f64,
+    pub margin_r: f64,
+    pub margin_v: f64,
+}
+
+/// A line at one instant, see [`Dialogue::state_at`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineState {
+    /// Set by `\pos`, or `\move` at that instant; `None` when the renderer places the line.
+    pub position: Option<(f64, f64)>,
+    /// Set by `\org`, the centre of rotation.
+    pub origin: Option<(f64, f64)>,
+    /// The last `\clip` or `\iclip`, a rectangle being moved by `\t`.
+    pub clip: Option<ClipShape>,
+    pub inverse_clip: bool,
+    /// The alpha `\fad` or `\fade` adds at that instant, 0 when the line is fully visible
+    /// and 255 when invisible. It is already applied to the colours of `runs`.
+    pub fade: u8,
+    pub runs: Vec<RunState>,
+}
+
+/// A segment of the line at one instant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunState {
+    pub segment: TextSegment,
+    /// The style with every `\t` evaluated.
+    pub style: ResolvedStyle,
+    /// The karaoke syllable the run belongs to and how far it is highlighted, from 0 to 1.
+    /// `\k` and `\ko` syllables jump from 0 to 1 when they start.
+    pub karaoke: Option<(KaraokeEffect, f64)>,
+}
+
+/// A segment of `Dialogue.text` other than an override block, with its style.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledRun {
+    pub segment: TextSegment,
+    pub style: ResolvedStyle,
+}
+
+impl ResolvedStyle {
+    /// `style` without any override tag, in the margins of `dialogue`.
+    pub fn new(style: &Styles, dialogue: &Dialogue) -> Self {
+        let margin = |line: f64, style: f32| if line != 0.0 { line } else { style.into() };
+        Self {
+            style: style.name.clone(),
+            font_name: style.font_name.clone(),
+            font_size: style.font_size.into(),
+            primary_colour: style.primary_colour,
+            secondary_colour: style.secondary_colour,
+            outline_colour: style.outline_colour,
+            back_colour: style.back_colour,
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+            strikeout: style.strikeout,
+            scale_x: style.scale_x.into(),
+            scale_y: style.scale_y.into(),
+            spacing: style.spacing.into(),
+            rotation_x: 0.0,
+            rotation_y: 0.0,
+            rotation_z: style.angle.into(),
+            shear_x: 0.0,
+            shear_y: 0.0,
+            border_style: style.border_style,
+            border_x: style.outline.into(),
+            border_y: style.outline.into(),
+            shadow_x: style.shadow.into(),
+            shadow_y: style.shadow.into(),
+            blur_edges: 0.0,
+            blur: 0.0,
+            alignment: style.alignment,
+            margin_l: margin(dialogue.margin_l, style.margin_l),
+            margin_r: margin(dialogue.margin_r, style.margin_r),
+            margin_v: margin(dialogue.margin_v, style.margin_v),
+        }
+    }
+
+    /// Resets everything `\r` does to `style`, keeping the line's alignment and margins.
+    pub fn reset(&mut self, style: &Styles) {
+        let (alignment, margin_l, margin_r, margin_v) =
+            (self.alignment, self.margin_l, self.margin_r, self.margin_v);
+        *self = Self {
+            alignment,
+            margin_l,
+            margin_r,
+            margin_v,
+            ..Self::new(style, &Dialogue::default())
+        };
+    }
+
+    /// Applies `tag`, tags without argument going back to `base`, the style `\r` last
+    /// reset to. `\r` itself, `\t` and the tags that apply to the whole line are left to
+    /// the caller.
+    pub fn apply(&mut self, tag: &Tag, base: &Styles) {
+        match tag {
+            Tag::Bold(b) => self.bold = b.map_or(base.bold, |b| b == 1 || b >= 700),
+            Tag::Italic(i) => self.italic = i.unwrap_or(base.italic),
+            Tag::Underline(u) => self.underline = u.unwrap_or(base.underline),
+            Tag::StrikeOut(s) => self.strikeout = s.unwrap_or(base.strikeout),
+            Tag::Border(v) => {
+                self.border_x = v.unwrap_or(base.outline.into());
+                self.border_y = self.border_x;
+            }
+            Tag::BorderX(v) => self.border_x = v.unwrap_or(base.outline.into()),
+            Tag::BorderY(v) => self.border_y = v.unwrap_or(base.outline.into()),
+            Tag::Shadow(v) => {
+                self.shadow_x = v.unwrap_or(base.shadow.into());
+                self.shadow_y = self.shadow_x;
+            }
+            Tag::ShadowX(v) => self.shadow_x = v.unwrap_or(base.shadow.into()),
+            Tag::ShadowY(v) => self.shadow_y = v.unwrap_or(base.shadow.into()),
+            Tag::BlurEdges(v) => self.blur_edges = v.unwrap_or_default(),
+            Tag::Blur(v) => self.blur = v.unwrap_or_default(),
+            Tag::FontName(name) => {
+                self.font_name = name.clone().unwrap_or_else(|| base.font_name.clone())
+            }
+            Tag::FontSize(v) => {
+                self.font_size = v.filter(|v| *v > 0.0).unwrap_or(base.font_size.into())
+            }
+            Tag::ScaleX(v) => self.scale_x = v.unwrap_or(base.scale_x.into()),
+            Tag::ScaleY(v) => self.scale_y = v.unwrap_or(base.scale_y.into()),
+            Tag::Spacing(v) => self.spacing = v.unwrap_or(base.spacing.into()),
+            Tag::RotationX(v) => self.rotation_x = v.unwrap_or_default(),
+            Tag::RotationY(v) => self.rotation_y = v.unwrap_or_default(),
+            Tag::RotationZ(v) => self.rotation_z = v.unwrap_or(base.angle.into()),
+            Tag::ShearX(v) => self.shear_x = v.unwrap_or_default(),
+            Tag::ShearY(v) => self.shear_y = v.unwrap_or_default(),
+            Tag::Colour(index, colour) => {
+                let new = colour.unwrap_or_else(|| style_colour(base, *index));
+                if let Some(c) = self.colour_mut(*index) {
+                    *c = Colour { a: c.a, ..new };
+                }
+            }
+            Tag::Alpha(alpha) => {
+                for index in 1..=4 {
+                    let a = alpha.unwrap_or(style_colour(base, index).a);
+                    if let Some(c) = self.colour_mut(index) {
+                        c.a = a;
+                    }
+                }
+            }
+            Tag::ColourAlpha(index, alpha) => {
+                let a = alpha.unwrap_or(style_colour(base, *index).a);
+                if let Some(c) = self.colour_mut(*index) {
+                    c.a = a;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves every animatable value a fraction `k` of the way to `target`, as `\t` does.
+    pub fn transform_to(&mut self, target: &Self, k: f64) {
+        for (value, to) in [
+            (&mut self.font_size, target.font_size),
+            (&mut self.scale_x, target.scale_x),
+            (&mut self.scale_y, target.scale_y),
+            (&mut self.spacing, target.spacing),
+            (&mut self.rotation_x, target.rotation_x),
+            (&mut self.rotation_y, target.rotation_y),
+            (&mut self.rotation_z, target.rotation_z),
+            (&mut self.shear_x, target.shear_x),
+            (&mut self.shear_y, target.shear_y),
+            (&mut self.border_x, target.border_x),
+            (&mut self.border_y, target.border_y),
+            (&mut self.shadow_x, target.shadow_x),
+            (&mut self.shadow_y, target.shadow_y),
+            (&mut self.blur_edges, target.blur_edges),
+            (&mut self.blur, target.blur),
+        ] {
+            *value = lerp(*value, to, k);
+        }
+        let channel = |from: u8, to: u8| lerp(from.into(), to.into(), k).round() as u8;
+        for (colour, to) in [
+            (&mut self.primary_colour, target.primary_colour),
+            (&mut self.secondary_colour, target.secondary_colour),
+            (&mut self.outline_colour, target.outline_colour),
+            (&mut self.back_colour, target.back_colour),
+        ] {
+            *colour = Colour::new(
+                channel(colour.r, to.r),
+                channel(colour.g, to.g),
+                channel(colour.b, to.b),
+                channel(colour.a, to.a),
+            );
+        }
+    }
+
+    /// The colour set by `\1c` to `\4c`.
+    pub fn colour_mut(&mut self, index: u8) -> Option<&mut Colour> {
+        match index {
+            1 => Some(&mut self.primary_colour),
+            2 => Some(&mut self.secondary_colour),
+            3 => Some(&mut self.outline_colour),
+            4 => Some(&mut self.back_colour),
+            _ => None,
+        }
+    }
+}
+
+fn style_colour(style: &Styles, index: u8) -> Colour {
+    match index {
+        1 => style.primary_colour,
+        2 => style.secondary_colour,
+        3 => style.outline_colour,
+        _ => style.back_colour,
+    }
+}
+
+/// Finds a style the way libass does: the last one with that name, ignoring a leading `*`.
+pub(crate) fn find_style<'a>(styles: &'a [Styles], name: &str) -> Option<&'a Styles> {
+    let name = name.strip_prefix('*').unwrap_or(name);
+    styles.iter().rev().find(|style| style.name == name)
+}
+
+/// The style of `dialogue`, falling back to `Default` or the first style as libass does.
+pub(crate) fn line_style<'a>(styles: &'a [Styles], dialogue: &Dialogue) -> Option<&'a Styles> {
+    find_style(styles, &dialogue.style)
+        .or_else(|| find_style(styles, "Default"))
+        .or(styles.first())
+}
+
+pub(crate) fn styled_runs(dialogue: &Dialogue, styles: &[Styles]) -> Vec<StyledRun> {
+    resolve(dialogue, styles, None)
+        .runs
+        .into_iter()
+        .map(|run| StyledRun {
+            segment: run.segment,
+            style: run.style,
+        })
+        .collect()
+}
+
+pub(crate) fn state_at(dialogue: &Dialogue, styles: &[Styles], time: Timestamp) -> LineState {
+    let duration = (dialogue.end - dialogue.start).as_millis() as f64;
+    let now = (time - dialogue.start).as_millis() as f64;
+    resolve(dialogue, styles, Some((now.min(duration), duration)))
+}
+
+/// Walks the text applying its tags. Without `time`, a `(now, duration)` pair in
+/// milliseconds from the line's start, the line is resolved as if nothing was animated.
+fn resolve(dialogue: &Dialogue, styles: &[Styles], time: Option<(f64, f64)>) -> LineState {
+    let fallback = Styles::default();
+    let line = line_style(styles, dialogue).unwrap_or(&fallback);
+    let segments = dialogue.parse_text();
+
+    let mut state = LineState {
+        position: None,
+        origin: None,
+        clip: None,
+        inverse_clip: false,
+        fade: 0,
+        runs: vec![],
+    };
+    let mut style = ResolvedStyle::new(line, dialogue);
+    // The first `\an` or `\a` applies to the whole line.
+    let first_alignment = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            TextSegment::Override(items) => Some(items),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|item| match item {
+            OverrideItem::Tag(Tag::Alignment(a) | Tag::LegacyAlignment(a)) => Some(*a),
+            _ => None,
+        });
+    if let Some(alignment) = first_alignment {
+        style.alignment = alignment.unwrap_or(line.alignment);
+    }
+    let mut base = line;
+    // Start and duration of the current karaoke syllable, in milliseconds.
+    let mut syllable: Option<(KaraokeEffect, f64, f64)> = None;
+    let mut karaoke_end = 0.0;
+    // Like `\pos`, only the first `\fad` or `\fade` counts.
+    let mut faded = false;
+    for segment in &segments {
+        let TextSegment::Override(items) = segment else {
+            let karaoke = syllable
+                .zip(time)
+                .map(|((effect, start, length), (now, _))| {
+                    let progress = match effect {
+                        KaraokeEffect::Sweep if length > 0.0 => (now - start) / length,
+                        _ if now >= start => 1.0,
+                        _ => 0.0,
+                    };
+                    (effect, progress.clamp(0.0, 1.0))
+                });
+            state.runs.push(RunState {
+                segment: segment.clone(),
+                style: style.clone(),
+                karaoke,
+            });
+            continue;
+        };
+        for item in items {
+            let OverrideItem::Tag(tag) = item else {
+                continue;
+            };
+            match tag {
+                Tag::Reset(name) => {
+                    base = name
+                        .as_deref()
+                        .and_then(|name| find_style(styles, name))
+                        .unwrap_or(line);
+                    style.reset(base);
+                }
+                Tag::Position { x, y } if state.position.is_none() => {
+                    state.position = Some((*x, *y));
+                }
+                Tag::Move {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    times,
+                } if state.position.is_none() => {
+                    let k = match time {
+                        Some((now, duration)) => {
+                            // Reversed times are swapped, as libass does.
+                            let (t1, t2) = match times {
+                                Some((t1, t2)) if *t1 > 0 || *t2 > 0 => {
+                                    ((*t1).min(*t2) as f64, (*t1).max(*t2) as f64)
+                                }
+                                _ => (0.0, duration),
+                            };
+                            progress(now, t1, t2)
+                        }
+                        None => 0.0,
+                    };
+                    state.position = Some((lerp(*x1, *x2, k), lerp(*y1, *y2, k)));
+                }
+                Tag::Origin { x, y } if state.origin.is_none() => {
+                    state.origin = Some((*x, *y));
+                }
+                Tag::Fade { fade_in, fade_out } if !faded => {
+                    faded = true;
+                    if let Some((now, duration)) = time {
+                        let times = [0.0, *fade_in as f64, duration - *fade_out as f64, duration];
+                        state.fade = fade_alpha(now, [255, 0, 255], times);
+                    }
+                }
+                Tag::ComplexFade { alphas, times } if !faded => {
+                    faded = true;
+                    if let Some((now, _)) = time {
+                        state.fade = fade_alpha(now, *alphas, times.map(f64::from));
+                    }
+                }
+                Tag::Clip { inverse, shape } => {
+                    state.clip = Some(shape.clone());
+                    state.inverse_clip = *inverse;
+                }
+                Tag::Karaoke(effect, centis) => {
+                    let length = centis * 10.0;
+                    syllable = Some((*effect, karaoke_end, length));
+                    karaoke_end += length;
+                }
+                Tag::Transform { times, accel, tags } => {
+                    let Some((now, duration)) = time else {
+                        continue;
+                    };
+                    // As in libass, a `t2` of 0 is the end of the line.
+                    let (t1, t2) = match *times {
+                        Some((t1, 0)) => (t1 as f64, duration),
+                        Some((t1, t2)) => (t1 as f64, t2 as f64),
+                        None => (0.0, duration),
+                    };
+                    let k = progress(now, t1, t2).powf(accel.unwrap_or(1.0));
+                    let mut target = style.clone();
+                    for tag in tags {
+                        if let Tag::Clip {
+                            shape: to @ ClipShape::Rectangle { .. },
+                            ..
+                        } = tag
+                        {
+                            if let Some(from @ ClipShape::Rectangle { .. }) = &state.clip {
+                                state.clip = Some(lerp_clip(from, to, k));
+                            }
+                        }
+                        target.apply(tag, base);
+                    }
+                    style.transform_to(&target, k);
+                }
+                tag => style.apply(tag, base),
+            }
+        }
+    }
+
+    if state.fade > 0 {
+        for run in &mut state.runs {
+            for index in 1..=4 {
+                if let Some(c) = run.style.colour_mut(index) {
+                    c.a = (c.a as u32 + (255 - c.a as u32) * state.fade as u32 / 255) as u8;
+                }
+            }
+        }
+    }
+    state
+}
+
+/// How far `now` is from `t1` to `t2`, between 0 and 1.
+fn progress(now: f64, t1: f64, t2: f64) -> f64 {
+    if now < t1 {
+        0.0
+    } else if now >= t2 {
+        1.0
+    } else {
+        (now - t1) / (t2 - t1)
+    }
+}
+
+fn lerp(from: f64, to: f64, k: f64) -> f64 {
+    from + (to - from) * k
+}
+
+fn lerp_clip(from: &ClipShape, to: &ClipShape, k: f64) -> ClipShape {
+    match (from, to) {
+        (
+            ClipShape::Rectangle { x1, y1, x2, y2 },
+            ClipShape::Rectangle {
+                x1: to_x1,
+                y1: to_y1,
+                x2: to_x2,
+                y2: to_y2,
+            },
+        ) => ClipShape::Rectangle {
+            x1: lerp(*x1, *to_x1, k),
+            y1: lerp(*y1, *to_y1, k),
+            x2: lerp(*x2, *to_x2, k),
+            y2: lerp(*y2, *to_y2, k),
+        },
+        _ => to.clone(),
+    }
+}
+
+/// The alpha of `\fade(a1,a2,a3,t1,t2,t3,t4)` at `now`.
+fn fade_alpha(now: f64, [a1, a2, a3]: [u8; 3], [t1, t2, t3, t4]: [f64; 4]) -> u8 {
+    let (a1, a2, a3) = (a1 as f64, a2 as f64, a3 as f64);
+    let alpha = if now < t1 {
+        a1
+    } else if now < t2 {
+        lerp(a1, a2, (now - t1) / (t2 - t1))
+    } else if now < t3 {
+        a2
+    } else if now < t4 {
+        lerp(a2, a3, (now - t3) / (t4 - t3))
+    } else {
+        a3
+    };
+    alpha.round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{
+        Alignment, ClipShape, Colour, Dialogue, KaraokeEffect, Styles, TextSegment, Timestamp,
+    };
+
+    #[test]
+    fn test_styled_runs() {
+        let styles = [
+            Styles {
+                name: "Default".to_owned(),
+                outline: 3.0,
+                ..Styles::default()
+            },
+            Styles {
+                name: "Sign".to_owned(),
+                font_name: "Sign Font".to_owned(),
+                italic: true,
+                ..Styles::default()
+            },
+        ];
+        let dialogue = Dialogue {
+            style: "Missing".to_owned(),
+            margin_v: 50.0,
+            text: r"A{\b1\bord5\c&H0000FF&\alpha&H80&\fs}B{\an7\bord\1a&H00&}C{\rSign}D{\r\an1}E"
+                .to_owned(),
+            ..Dialogue::default()
+        };
+        let runs = dialogue.styled_runs(&styles);
+        assert_eq!(runs.len(), 5);
+        assert_eq!(runs[0].segment, TextSegment::Text("A".to_owned()));
+        assert!(runs
+            .iter()
+            .all(|run| run.style.alignment == Alignment::TopLeft && run.style.margin_v == 50.0));
+        assert_eq!(runs[0].style.style, "Default");
+        assert!(!runs[0].style.bold);
+
+        let b = &runs[1].style;
+        assert!(b.bold);
+        assert_eq!((b.border_x, b.border_y), (5.0, 5.0));
+        assert_eq!(b.primary_colour, Colour::new(0xFF, 0, 0, 0x80));
+        assert_eq!(b.outline_colour.a, 0x80);
+        assert_eq!(b.font_size, 48.0);
+
+        let c = &runs[2].style;
+        assert_eq!(c.border_x, 3.0);
+        assert_eq!(c.primary_colour, Colour::new(0xFF, 0, 0, 0));
+
+        let d = &runs[3].style;
+        assert_eq!(
+            (d.style.as_str(), d.font_name.as_str()),
+            ("Sign", "Sign Font")
+        );
+        assert!(d.italic && !d.bold);
+        assert_eq!(d.border_x, 2.0);
+
+        assert_eq!(runs[4].style.style, "Default");
+        assert_eq!(runs[4].style.margin_l, 10.0);
+    }
+
+    #[test]
+    fn test_state_at() {
+        let dialogue = Dialogue {
+            start: Timestamp::new(0, 0, 10, 0),
+            end: Timestamp::new(0, 0, 12, 0),
+            text: r"{\move(0,0,100,200,0,1000)\fad(500,500)\clip(0,0,10,10)\t(0,1000,2,\bord4\c&HFF0000&\clip(10,10,20,20))\k50}A{\kf100}B{\ko50}C".to_owned(),
+            ..Dialogue::default()
+        };
+        let state = dialogue.state_at(&[], Timestamp::new(0, 0, 10, 50));
+        assert_eq!(state.position, Some((50.0, 100.0)));
+        assert_eq!(state.fade, 0);
+        assert_eq!(
+            state.clip,
+            Some(ClipShape::Rectangle {
+                x1: 2.5,
+                y1: 2.5,
+                x2: 12.5,
+                y2: 12.5
+            })
+        );
+        let a = &state.runs[0];
+        assert_eq!(a.style.border_x, 2.5);
+        assert_eq!(a.style.primary_colour, Colour::new(0xBF, 0xBF, 0xFF, 0));
+        assert_eq!(a.karaoke, Some((KaraokeEffect::Instant, 1.0)));
+        assert_eq!(state.runs[1].karaoke, Some((KaraokeEffect::Sweep, 0.0)));
+
+        let state = dialogue.state_at(&[], Timestamp::new(0, 0, 11, 0));
+        assert_eq!(state.runs[1].karaoke, Some((KaraokeEffect::Sweep, 0.5)));
+        assert_eq!(state.runs[2].karaoke, Some((KaraokeEffect::Outline, 0.0)));
+        assert_eq!(state.runs[2].style.border_x, 4.0);
+
+        let state = dialogue.state_at(&[], Timestamp::new(0, 0, 11, 75));
+        assert_eq!(state.position, Some((100.0, 200.0)));
+        assert_eq!(state.fade, 128);
+        assert_eq!(state.runs[0].style.primary_colour.a, 128);
+        assert_eq!(state.runs[2].karaoke, Some((KaraokeEffect::Outline, 1.0)));
+
+        let dialogue = Dialogue {
+            text: r"{\fad(100,0)\fad(500,0)}A".to_owned(),
+            ..dialogue
+        };
+        let state = dialogue.state_at(&[], Timestamp::new(0, 0, 10, 20));
+        assert_eq!(state.fade, 0);
+
+        let dialogue = Dialogue {
+            text: r"{\move(0,0,100,200,1000,0)\t(0,0,\fs80)}A".to_owned(),
+            ..dialogue
+        };
+        let state = dialogue.state_at(&[], Timestamp::new(0, 0, 10, 0));
+        assert_eq!(state.position, Some((0.0, 0.0)));
+        assert_eq!(state.runs[0].style.font_size, 48.0);
+        let state = dialogue.state_at(&[], Timestamp::new(0, 0, 10, 50));
+        assert_eq!(state.position, Some((50.0, 100.0)));
+        assert_eq!(state.runs[0].style.font_size, 56.0);
+    }
+}
@@ -0,0 +1,479 @@
+use super::colour::Colour;
+use super::document::{Alignment, WrapStyle};
+use super::drawing::Drawing;
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter, Write};
+use std::mem::{discriminant, Discriminant};
+
+/// A piece of `Dialogue.text`, see [`Dialogue::parse_text`](super::document::Dialogue::parse_text).
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextSegment {
+    /// Text as written, without the line breaks and hard spaces, and with `\{` and `\}`
+    /// read as braces.
+    Text(String),
+    /// A `{...}` block.
+    Override(Vec<OverrideItem>),
+    /// `\N`
+    HardBreak,
+    /// `\n`, a line break only with `\q2` or `WrapStyle: 2`
+    SoftBreak,
+    /// `\h`
+    HardSpace,
+    /// Drawing commands written while `\p` is on.
+    Drawing(Drawing),
+}
+
+/// What an override block holds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OverrideItem {
+    Tag(Tag),
+    /// Text in a block that isn't a tag, which renderers ignore.
+    Comment(String),
+}
+
+/// An override tag.
+///
+/// `None` stands for a tag written without its argument, e.g. `\bord`, which resets
+/// the value to the one of the line's style. Times are in milliseconds from the start
+/// of the line, except karaoke durations which are in centiseconds.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    /// `\b1`, `\b0` or a font weight such as `\b700`
+    Bold(Option<i32>),
+    /// `\i`
+    Italic(Option<bool>),
+    /// `\u`
+    Underline(Option<bool>),
+    /// `\s`
+    StrikeOut(Option<bool>),
+    /// `\bord`
+    Border(Option<f64>),
+    /// `\xbord`
+    BorderX(Option<f64>),
+    /// `\ybord`
+    BorderY(Option<f64>),
+    /// `\shad`
+    Shadow(Option<f64>),
+    /// `\xshad`
+    ShadowX(Option<f64>),
+    /// `\yshad`
+    ShadowY(Option<f64>),
+    /// `\be`
+    BlurEdges(Option<f64>),
+    /// `\blur`
+    Blur(Option<f64>),
+    /// `\fn`
+    FontName(Option<String>),
+    /// `\fs`
+    FontSize(Option<f64>),
+    /// `\fe`
+    FontEncoding(Option<i32>),
+    /// `\fscx`
+    ScaleX(Option<f64>),
+    /// `\fscy`
+    ScaleY(Option<f64>),
+    /// `\fsp`
+    Spacing(Option<f64>),
+    /// `\frx`
+    RotationX(Option<f64>),
+    /// `\fry`
+    RotationY(Option<f64>),
+    /// `\frz`, or its alias `\fr`
+    RotationZ(Option<f64>),
+    /// `\fax`
+    ShearX(Option<f64>),
+    /// `\fay`
+    ShearY(Option<f64>),
+    /// `\c` or `\1c` to `\4c`, the index being 1 to 4. The colour's `a` is always 0,
+    /// alpha has its own tags.
+    Colour(u8, Option<Colour>),
+    /// `\alpha`, sets all four alphas
+    Alpha(Option<u8>),
+    /// `\1a` to `\4a`, the index being 1 to 4
+    ColourAlpha(u8, Option<u8>),
+    /// `\an`, `None` also when the number is not 1 to 9
+    Alignment(Option<Alignment>),
+    /// `\a`, using the SSA numbering
+    LegacyAlignment(Option<Alignment>),
+    /// `\q`
+    WrapStyle(Option<WrapStyle>),
+    /// `\r` or `\rStyle name`
+    Reset(Option<String>),
+    /// `\k`, `\kf` (or `\K`) and `\ko`, with a duration in centiseconds
+    Karaoke(KaraokeEffect, f64),
+    /// `\p`, 0 turns drawing mode off, anything else is the drawing's scale
+    DrawingMode(u32),
+    /// `\pbo`
+    BaselineOffset(Option<f64>),
+    /// `\pos(x,y)`
+    Position { x: f64, y: f64 },
+    /// `\move(x1,y1,x2,y2[,t1,t2])`
+    Move {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        times: Option<(i32, i32)>,
+    },
+    /// `\org(x,y)`
+    Origin { x: f64, y: f64 },
+    /// `\fad(in,out)`, or `\fade` with two arguments
+    Fade { fade_in: i32, fade_out: i32 },
+    /// `\fade(a1,a2,a3,t1,t2,t3,t4)`
+    ComplexFade { alphas: [u8; 3], times: [i32; 4] },
+    /// `\t([t1,t2,][accel,]tags)`
+    Transform {
+        times: Option<(i32, i32)>,
+        accel: Option<f64>,
+        tags: Vec<Tag>,
+    },
+    /// `\clip(...)`, or `\iclip(...)` when `inverse`
+    Clip { inverse: bool, shape: ClipShape },
+    /// A tag this crate doesn't know, or whose arguments it can't read, as written
+    /// after the backslash.
+    Unknown(String),
+}
+
+/// How a karaoke syllable is highlighted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KaraokeEffect {
+    /// `\k`: switches from secondary to primary colour at once
+    Instant,
+    /// `\kf` or `\K`: fills from left to right over the duration
+    Sweep,
+    /// `\ko`: like `\k`, with the outline hidden until highlighted
+    Outline,
+}
+
+/// The area of a `\clip` or `\iclip`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClipShape {
+    /// `\clip(x1,y1,x2,y2)`
+    Rectangle { x1: f64, y1: f64, x2: f64, y2: f64 },
+    /// `\clip([scale,]drawing)`
+    Vector {
+        scale: Option<u32>,
+        drawing: Drawing,
+    },
+}
+
+/// How [`write_text`] writes segments back into `Dialogue.text`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextWriteOptions {
+    /// Writes `{\b1}{\i1}` as `{\b1\i1}`.
+    pub merge_blocks: bool,
+    /// Leaves out tags that have no effect: all but the first `\pos`, `\move`, `\org`,
+    /// `\fad`, `\fade`, `\an` or `\a` of the line, and a tag set again later in the
+    /// same block.
+    pub drop_redundant: bool,
+    /// Rounds numbers to this many decimals, trailing zeros are never written.
+    pub precision: Option<usize>,
+}
+
+impl TextWriteOptions {
+    /// The shortest text that renders the same, up to the third decimal.
+    pub fn minimal() -> Self {
+        Self {
+            merge_blocks: true,
+            drop_redundant: true,
+            precision: Some(3),
+        }
+    }
+}
+
+/// Writes segments back into the text of a `Dialogue`. With the default options,
+/// `write_text(&dialogue.parse_text(), &Default::default())` is `dialogue.text` with tags
+/// in their canonical form, e.g. `\fr` written as `\frz` and `\K` as `\kf`.
+pub fn write_text(segments: &[TextSegment], options: &TextWriteOptions) -> String {
+    let mut text = String::new();
+    let mut blocks: Vec<Vec<&OverrideItem>> = vec![];
+    let mut first_tags = HashSet::new();
+    let mut flush = |text: &mut String, blocks: &mut Vec<Vec<&OverrideItem>>| {
+        for block in blocks.drain(..) {
+            let had_items = !block.is_empty();
+            let block = match options.drop_redundant {
+                true => drop_redundant(block, &mut first_tags),
+                false => block,
+            };
+            if had_items && block.is_empty() {
+                continue;
+            }
+            text.push('{');
+            for item in block {
+                match item {
+                    OverrideItem::Tag(tag) => tag.write(text, options.precision),
+                    OverrideItem::Comment(comment) => text.push_str(comment),
+                }
+            }
+            text.push('}');
+        }
+    };
+    for segment in segments {
+        if let TextSegment::Override(items) = segment {
+            let merge = options.merge_blocks
+                && !blocks.is_empty()
+                && !matches!(items.first(), Some(OverrideItem::Comment(_)));
+            match blocks.last_mut() {
+                Some(block) if merge => block.extend(items),
+                _ => blocks.push(items.iter().collect()),
+            }
+            continue;
+        }
+        flush(&mut text, &mut blocks);
+        match segment {
+            TextSegment::Text(run) => text.push_str(&run.replace('{', "\\{")),
+            TextSegment::Drawing(drawing) => {
+                let _ = write!(text, "{}", drawing);
+            }
+            TextSegment::HardBreak => text.push_str("\\N"),
+            TextSegment::SoftBreak => text.push_str("\\n"),
+            TextSegment::HardSpace => text.push_str("\\h"),
+            TextSegment::Override(_) => unreachable!(),
+        }
+    }
+    flush(&mut text, &mut blocks);
+    text
+}
+
+/// Which occurrence of a tag renderers use.
+enum Applies {
+    /// Only the first in the line.
+    First(&'static str),
+    /// The last in a block, unless a `\t` or `\r` comes between.
+    Last(Discriminant<Tag>, u8),
+    Always,
+}
+
+fn applies(tag: &Tag) -> Applies {
+    match tag {
+        Tag::Position { .. } | Tag::Move { .. } => Applies::First("position"),
+        Tag::Origin { .. } => Applies::First("origin"),
+        Tag::Fade { .. } | Tag::ComplexFade { .. } => Applies::First("fade"),
+        Tag::Alignment(_) | Tag::LegacyAlignment(_) => Applies::First("alignment"),
+        Tag::Karaoke(..) | Tag::Transform { .. } | Tag::Reset(_) | Tag::Unknown(_) => {
+            Applies::Always
+        }
+        Tag::Colour(index, _) | Tag::ColourAlpha(index, _) => {
+            Applies::Last(discriminant(tag), *index)
+        }
+        _ => Applies::Last(discriminant(tag), 0),
+    }
+}
+
+fn drop_redundant<'a>(
+    block: Vec<&'a OverrideItem>,
+    first_tags: &mut HashSet<&'static str>,
+) -> Vec<&'a OverrideItem> {
+    let overridden = |i: usize, key: &(Discriminant<Tag>, u8)| {
+        for item in &block[i + 1..] {
+            if let OverrideItem::Tag(tag) = item {
+                match applies(tag) {
+                    Applies::Last(d, index) if (d, index) == *key => return true,
+                    _ if matches!(tag, Tag::Transform { .. } | Tag::Reset(_)) => return false,
+                    _ => {}
+                }
+            }
+        }
+        false
+    };
+    let mut kept = vec![];
+    for (i, item) in block.iter().enumerate() {
+        let keep = match item {
+            OverrideItem::Tag(tag) => match applies(tag) {
+                Applies::First(name) => first_tags.insert(name),
+                Applies::Last(d, index) => !overridden(i, &(d, index)),
+                Applies::Always => true,
+            },
+            OverrideItem::Comment(_) => true,
+        };
+        if keep {
+            kept.push(*item);
+        }
+    }
+    kept
+}
+
+impl Tag {
+    fn write(&self, out: &mut String, precision: Option<usize>) {
+        let n = |n: f64| number(n, precision);
+        let opt = |value: &Option<f64>| value.map(n).unwrap_or_default();
+        let flag = |value: &Option<bool>| value.map(|v| i32::from(v).to_string());
+        let _ = match self {
+            Self::Bold(b) => write!(out, "\\b{}", display(b)),
+            Self::Italic(i) => write!(out, "\\i{}", flag(i).unwrap_or_default()),
+            Self::Underline(u) => write!(out, "\\u{}", flag(u).unwrap_or_default()),
+            Self::StrikeOut(s) => write!(out, "\\s{}", flag(s).unwrap_or_default()),
+            Self::Border(v) => write!(out, "\\bord{}", opt(v)),
+            Self::BorderX(v) => write!(out, "\\xbord{}", opt(v)),
+            Self::BorderY(v) => write!(out, "\\ybord{}", opt(v)),
+            Self::Shadow(v) => write!(out, "\\shad{}", opt(v)),
+            Self::ShadowX(v) => write!(out, "\\xshad{}", opt(v)),
+            Self::ShadowY(v) => write!(out, "\\yshad{}", opt(v)),
+            Self::BlurEdges(v) => write!(out, "\\be{}", opt(v)),
+            Self::Blur(v) => write!(out, "\\blur{}", opt(v)),
+            Self::FontName(name) => write!(out, "\\fn{}", display(name)),
+            Self::FontSize(v) => write!(out, "\\fs{}", opt(v)),
+            Self::FontEncoding(e) => write!(out, "\\fe{}", display(e)),
+            Self::ScaleX(v) => write!(out, "\\fscx{}", opt(v)),
+            Self::ScaleY(v) => write!(out, "\\fscy{}", opt(v)),
+            Self::Spacing(v) => write!(out, "\\fsp{}", opt(v)),
+            Self::RotationX(v) => write!(out, "\\frx{}", opt(v)),
+            Self::RotationY(v) => write!(out, "\\fry{}", opt(v)),
+            Self::RotationZ(v) => write!(out, "\\frz{}", opt(v)),
+            Self::ShearX(v) => write!(out, "\\fax{}", opt(v)),
+            Self::ShearY(v) => write!(out, "\\fay{}", opt(v)),
+            Self::Colour(1, colour) => write!(out, "\\c{}", tag_colour(colour)),
+            Self::Colour(index, colour) => write!(out, "\\{}c{}", index, tag_colour(colour)),
+            Self::Alpha(alpha) => write!(out, "\\alpha{}", tag_alpha(alpha)),
+            Self::ColourAlpha(index, alpha) => {
+                write!(out, "\\{}a{}", index, tag_alpha(alpha))
+            }
+            Self::Alignment(a) => write!(out, "\\an{}", display(&a.map(Alignment::numpad))),
+            Self::LegacyAlignment(a) => write!(out, "\\a{}", display(&a.map(Alignment::legacy))),
+            Self::WrapStyle(q) => match q {
+                Some(q) => write!(out, "\\q{:?}", q),
+                None => write!(out, "\\q"),
+            },
+            Self::Reset(style) => write!(out, "\\r{}", display(style)),
+            Self::Karaoke(effect, duration) => {
+                let name = match effect {
+                    KaraokeEffect::Instant => "k",
+                    KaraokeEffect::Sweep => "kf",
+                    KaraokeEffect::Outline => "ko",
+                };
+                write!(out, "\\{}{}", name, n(*duration))
+            }
+            Self::DrawingMode(scale) => write!(out, "\\p{}", scale),
+            Self::BaselineOffset(v) => write!(out, "\\pbo{}", opt(v)),
+            Self::Position { x, y } => write!(out, "\\pos({},{})", n(*x), n(*y)),
+            Self::Move {
+                x1,
+                y1,
+                x2,
+                y2,
+                times,
+            } => {
+                let _ = write!(out, "\\move({},{},{},{}", n(*x1), n(*y1), n(*x2), n(*y2));
+                if let Some((t1, t2)) = times {
+                    let _ = write!(out, ",{},{}", t1, t2);
+                }
+                write!(out, ")")
+            }
+            Self::Origin { x, y } => write!(out, "\\org({},{})", n(*x), n(*y)),
+            Self::Fade { fade_in, fade_out } => write!(out, "\\fad({},{})", fade_in, fade_out),
+            Self::ComplexFade { alphas, times } => write!(
+                out,
+                "\\fade({},{},{},{},{},{},{})",
+                alphas[0], alphas[1], alphas[2], times[0], times[1], times[2], times[3]
+            ),
+            Self::Transform { times, accel, tags } => {
+                out.push_str("\\t(");
+                if let Some((t1, t2)) = times {
+                    let _ = write!(out, "{},{},", t1, t2);
+                }
+                if let Some(accel) = accel {
+                    let _ = write!(out, "{},", n(*accel));
+                }
+                for tag in tags {
+                    tag.write(out, precision);
+                }
+                write!(out, ")")
+            }
+            Self::Clip { inverse, shape } => {
+                out.push_str(if *inverse { "\\iclip(" } else { "\\clip(" });
+                let _ = match shape {
+                    ClipShape::Rectangle { x1, y1, x2, y2 } => {
+                        write!(out, "{},{},{},{}", n(*x1), n(*y1), n(*x2), n(*y2))
+                    }
+                    ClipShape::Vector {
+                        scale: Some(scale),
+                        drawing,
+                    } => write!(out, "{},{}", scale, drawing),
+                    ClipShape::Vector {
+                        scale: None,
+                        drawing,
+                    } => write!(out, "{}", drawing),
+                };
+                write!(out, ")")
+            }
+            Self::Unknown(raw) => write!(out, "\\{}", raw),
+        };
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut tag = String::new();
+        self.write(&mut tag, None);
+        f.write_str(&tag)
+    }
+}
+
+fn display<T: Display>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+/// `&HBBGGRR&`, tags leave alpha out.
+fn tag_colour(colour: &Option<Colour>) -> String {
+    colour.map_or(String::new(), |c| {
+        format!("&H{:02X}{:02X}{:02X}&", c.b, c.g, c.r)
+    })
+}
+
+fn tag_alpha(alpha: &Option<u8>) -> String {
+    alpha.map_or(String::new(), |a| format!("&H{:02X}&", a))
+}
+
+/// Writes `n` without trailing zeros, rounded to `precision` decimals if given.
+fn number(n: f64, precision: Option<usize>) -> String {
+    let Some(precision) = precision else {
+        return n.to_string();
+    };
+    let n = format!("{:.*}", precision, n);
+    let n = match n.contains('.') {
+        true => n.trim_end_matches('0').trim_end_matches('.'),
+        false => &n,
+    };
+    match n {
+        "-0" => "0".to_string(),
+        n => n.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_text, TextWriteOptions};
+    use crate::prelude::Dialogue;
+
+    fn rewrite(text: &str, options: &TextWriteOptions) -> String {
+        let dialogue = Dialogue {
+            text: text.to_owned(),
+            ..Dialogue::default()
+        };
+        write_text(&dialogue.parse_text(), options)
+    }
+
+    #[test]
+    fn test_write_text_canonical() {
+        let text = r"{\an8\pos(960,50)\c&H00FF00&\3c&H0000FF&\alpha&H80&\b1 bold}Hello\Nworld\h!{\t(0,500,0.5,\frz360)\iclip(2,m 0 0 l 5 5)\fade(255,0,255,0,100,200,300)\xyz(1)}\n{\p1}m 0 0 l 10 0{\p0\fn\kf50}";
+        assert_eq!(rewrite(text, &TextWriteOptions::default()), text);
+        assert_eq!(
+            rewrite(r"{\fr10\K20\1c&HFF&\c}", &TextWriteOptions::default()),
+            r"{\frz10\kf20\c&H0000FF&\c}"
+        );
+        assert_eq!(
+            rewrite(r"a\{b\} {c", &TextWriteOptions::default()),
+            r"a\{b} \{c"
+        );
+    }
+
+    #[test]
+    fn test_write_text_minimal() {
+        let text =
+            r"{\pos(1,2)\bord2}{\bord3.14159\blur1}Hi{note}{\pos(3,4)\an8\1c&HFF&\t(\bord5)\bord6}";
+        assert_eq!(
+            rewrite(text, &TextWriteOptions::minimal()),
+            r"{\pos(1,2)\bord3.142\blur1}Hi{note\an8\c&H0000FF&\t(\bord5)\bord6}"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::parsers::{parse_timecodes, ParseError};
+use std::str::FromStr;
+
+/// Which time of a frame [`Timecodes`] conversions refer to, as in Aegisub.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameTime {
+    /// The time the frame is shown at.
+    Exact,
+    /// A start time for a line that should first be shown on the frame.
+    Start,
+    /// An end time for a line that should last be shown on the frame.
+    End,
+}
+
+/// The time of every frame of a video, from an mkvmerge timecode file or a constant rate.
+///
+/// `FromStr` accepts timecode format v1 and v2 files, and constant rates such as `25`,
+/// `23.976` or `24000/1001`. Times are in milliseconds, frames count from zero, and both
+/// go on past the end of a file at its rate: the assumed one of a v1 file and the average
+/// one of a v2 file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timecodes {
+    /// The rounded time of each frame listed in the file, at least one.
+    timecodes: Vec<i64>,
+    /// The exact time of the last listed frame.
+    last: f64,
+    /// The rate past the last listed frame, in frames per second.
+    fps: f64,
+}
+
+impl Timecodes {
+    /// # Panics
+    /// If `fps` isn't a positive number.
+    pub fn constant(fps: f64) -> Self {
+        assert!(fps.is_finite() && fps > 0.0, "frame rate must be positive");
+        Self::new(vec![0], 0.0, fps)
+    }
+
+    pub(crate) fn new(timecodes: Vec<i64>, last: f64, fps: f64) -> Self {
+        Self {
+            timecodes,
+            last,
+            fps,
+        }
+    }
+
+    /// The frame rate, when constant.
+    pub(crate) fn constant_fps(&self) -> Option<f64> {
+        (self.timecodes.len() == 1).then_some(self.fps)
+    }
+
+    /// The frame a line starting or ending at `ms`, or video at `ms`, is on.
+    ///
+    /// `Exact` is the last frame shown at or before `ms`, `Start` the first frame starting
+    /// at or after `ms` and `End` the last frame starting before `ms`.
+    pub fn frame_at(&self, ms: i64, kind: FrameTime) -> i64 {
+        match kind {
+            FrameTime::Exact => self.exact_frame(ms),
+            FrameTime::Start => self.exact_frame(ms - 1) + 1,
+            FrameTime::End => self.exact_frame(ms - 1),
+        }
+    }
+
+    /// The time of `frame`.
+    ///
+    /// `Start` and `End` are halfway to the previous and to the next frame, the times
+    /// least likely to land on another frame once rounded, so that
+    /// `frame_at(time_at(n, kind), kind) == n`.
+    pub fn time_at(&self, frame: i64, kind: FrameTime) -> i64 {
+        let halfway = |from: i64, to: i64| from + (to - from + 1).div_euclid(2);
+        match kind {
+            FrameTime::Exact => self.exact_time(frame),
+            FrameTime::Start => halfway(self.exact_time(frame - 1), self.exact_time(frame)),
+            FrameTime::End => halfway(self.exact_time(frame), self.exact_time(frame + 1)),
+        }
+    }
+
+    fn exact_time(&self, frame: i64) -> i64 {
+        if frame < 0 {
+            return (frame as f64 * 1000.0 / self.fps) as i64;
+        }
+        match self.timecodes.get(frame as usize) {
+            Some(&time) => time,
+            None => {
+                let past = (frame + 1 - self.timecodes.len() as i64) as f64;
+                (self.last + past * 1000.0 / self.fps).round() as i64
+            }
+        }
+    }
+
+    fn exact_frame(&self, ms: i64) -> i64 {
+        let last = *self.timecodes.last().unwrap_or(&0);
+        if ms < 0 {
+            return (ms as f64 * self.fps / 1000.0).floor() as i64;
+        }
+        if ms <= last {
+            return self.timecodes.partition_point(|&time| time <= ms) as i64 - 1;
+        }
+        // Estimate, then correct for the rounding of the times.
+        let mut frame = self.timecodes.len() as i64 - 1
+            + ((ms as f64 - self.last) * self.fps / 1000.0).floor() as i64;
+        while self.exact_time(frame) > ms {
+            frame -= 1;
+        }
+        while self.exact_time(frame + 1) <= ms {
+            frame += 1;
+        }
+        frame
+    }
+}
+
+impl FromStr for Timecodes {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_timecodes(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameTime, Timecodes};
+    use crate::prelude::{Dialogue, EventFilter, ParseError, SubtitlesFile, Timestamp};
+
+    #[test]
+    fn test_timecodes() {
+        let ntsc: Timecodes = "24000/1001".parse().unwrap();
+        assert_eq!(ntsc.time_at(1, FrameTime::Exact), 42);
+        assert_eq!(ntsc.time_at(1, FrameTime::Start), 21);
+        assert_eq!(ntsc.time_at(1, FrameTime::End), 63);
+        assert_eq!(ntsc.frame_at(42, FrameTime::Exact), 1);
+        assert_eq!(ntsc.frame_at(42, FrameTime::Start), 1);
+        assert_eq!(ntsc.frame_at(42, FrameTime::End), 0);
+        assert_eq!(ntsc.frame_at(1001, FrameTime::Exact), 24);
+
+        let v1: Timecodes = "# timecode format v1\nAssume 25\n# comment\n2,3,50\n"
+            .parse()
+            .unwrap();
+        let times: Vec<_> = (0..7).map(|f| v1.time_at(f, FrameTime::Exact)).collect();
+        assert_eq!(times, [0, 40, 80, 100, 120, 160, 200]);
+        assert_eq!(v1.frame_at(119, FrameTime::Exact), 3);
+        assert_eq!(v1.frame_at(100_000, FrameTime::Exact), 2501);
+
+        let v2: Timecodes = "# timestamp format v2\r\n0\r\n50\r\n100.4\r\n200\r\n"
+            .parse()
+            .unwrap();
+        assert_eq!(v2.time_at(2, FrameTime::Exact), 100);
+        assert_eq!(v2.time_at(5, FrameTime::Exact), 333);
+        assert_eq!(v2.frame_at(150, FrameTime::Start), 3);
+        assert!(matches!(
+            "# timecode format v2\n0\n40\n20".parse::<Timecodes>(),
+            Err(ParseError::InvalidTimecodes { line: 4, .. })
+        ));
+
+        let mut file = SubtitlesFile {
+            events: vec![Dialogue {
+                start: Timestamp::new(0, 0, 1, 0),
+                end: Timestamp::new(0, 0, 2, 0),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        };
+        assert_eq!(file.events[0].start_frame(&ntsc), 24);
+        assert_eq!(file.events[0].end_frame(&ntsc), 47);
+        file.snap_to_frames(&ntsc, &EventFilter::default());
+        assert_eq!(file.events[0].start, Timestamp::new(0, 0, 0, 98));
+        assert_eq!(file.events[0].end, Timestamp::new(0, 0, 1, 98));
+    }
+}
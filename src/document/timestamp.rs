@@ -0,0 +1,169 @@
+use crate::parsers::{lenient_timestamp, strict_timestamp, ParseError};
+use std::fmt::{Display, Formatter, Result};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A point in time in a script, with the centisecond precision of the ASS format.
+///
+/// `Display` writes it as `H:MM:SS.cc`, `FromStr` only accepts that form.
+/// Arithmetic saturates at `0:00:00.00`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u32);
+
+impl Timestamp {
+    pub const ZERO: Self = Self(0);
+
+    /// # Panics
+    /// If the timestamp doesn't fit in `u32` centiseconds (about 497 days).
+    pub fn new(hours: u32, minutes: u32, seconds: u32, centis: u32) -> Self {
+        Self::checked_new(hours, minutes, seconds, centis).expect("timestamp out of range")
+    }
+
+    pub(crate) fn checked_new(hours: u32, minutes: u32, seconds: u32, centis: u32) -> Option<Self> {
+        hours
+            .checked_mul(3600)?
+            .checked_add(minutes.checked_mul(60)?)?
+            .checked_add(seconds)?
+            .checked_mul(100)?
+            .checked_add(centis)
+            .map(Self)
+    }
+
+    pub fn from_centis(centis: u32) -> Self {
+        Self(centis)
+    }
+
+    /// Rounds `millis` to the nearest centisecond, saturating at the largest timestamp.
+    pub fn from_millis(millis: u64) -> Self {
+        Self(u32::try_from(millis.saturating_add(5) / 10).unwrap_or(u32::MAX))
+    }
+
+    pub fn as_centis(self) -> u32 {
+        self.0
+    }
+
+    pub fn as_millis(self) -> u64 {
+        u64::from(self.0) * 10
+    }
+
+    pub fn hours(self) -> u32 {
+        self.0 / 360_000
+    }
+
+    pub fn minutes(self) -> u32 {
+        self.0 / 6000 % 60
+    }
+
+    pub fn seconds(self) -> u32 {
+        self.0 / 100 % 60
+    }
+
+    pub fn centis(self) -> u32 {
+        self.0 % 100
+    }
+
+    /// Parses a timestamp as leniently as Aegisub does, accepting e.g. `0:0:5.5` (five and
+    /// a half seconds) or `0:75:00` (one hour fifteen). libass reads the fraction as a
+    /// number of centiseconds instead, showing `0:0:5.5` at 5.05 seconds.
+    pub fn parse_lenient(s: &str) -> std::result::Result<Self, ParseError> {
+        lenient_timestamp(s, s, "timestamp")
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ParseError;
+
+    /// Parses `H:MM:SS.cc`, rejecting minutes or seconds of 60 and above.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        strict_timestamp(s, s, "timestamp")
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}:{:02}:{:02}.{:02}",
+            self.hours(),
+            self.minutes(),
+            self.seconds(),
+            self.centis()
+        )
+    }
+}
+
+fn duration_centis(duration: Duration) -> u32 {
+    u32::try_from((duration.as_millis() + 5) / 10).unwrap_or(u32::MAX)
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Self;
+
+    fn add(self, rhs: Duration) -> Self {
+        Self(self.0.saturating_add(duration_centis(rhs)))
+    }
+}
+
+impl AddAssign<Duration> for Timestamp {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Timestamp {
+    type Output = Self;
+
+    fn sub(self, rhs: Duration) -> Self {
+        Self(self.0.saturating_sub(duration_centis(rhs)))
+    }
+}
+
+impl SubAssign<Duration> for Timestamp {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Duration;
+
+    /// Time elapsed between `rhs` and `self`, zero if `rhs` is later.
+    fn sub(self, rhs: Self) -> Duration {
+        Duration::from_millis(Self(self.0.saturating_sub(rhs.0)).as_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timestamp;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamp_parse_and_format() {
+        let t: Timestamp = "1:02:03.45".parse().unwrap();
+        assert_eq!(t, Timestamp::new(1, 2, 3, 45));
+        assert_eq!(t.as_millis(), 3_723_450);
+        assert_eq!(t.to_string(), "1:02:03.45");
+        assert!("0:60:00.00".parse::<Timestamp>().is_err());
+        assert!("0:0:5.5".parse::<Timestamp>().is_err());
+        assert_eq!(
+            Timestamp::parse_lenient("0:0:5.5").unwrap(),
+            Timestamp::new(0, 0, 5, 50)
+        );
+        assert_eq!(
+            Timestamp::parse_lenient("0:75:00.125").unwrap(),
+            Timestamp::new(1, 15, 0, 13)
+        );
+    }
+
+    #[test]
+    fn test_timestamp_arithmetic() {
+        let t = Timestamp::new(0, 0, 1, 0);
+        assert_eq!(t + Duration::from_millis(1234), Timestamp::new(0, 0, 2, 23));
+        assert_eq!(t - Duration::from_secs(5), Timestamp::ZERO);
+        assert_eq!(Timestamp::new(0, 0, 3, 0) - t, Duration::from_secs(2));
+        assert!(t < Timestamp::new(0, 0, 1, 1));
+        assert_eq!(Timestamp::from_millis(u64::MAX), Timestamp::from_centis(u32::MAX));
+    }
+}
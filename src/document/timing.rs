@@ -0,0 +1,512 @@
+use super::document::{Dialogue, EventType, SubtitlesFile};
+use super::keyframes::Keyframes;
+use super::tags::{write_text, OverrideItem, Tag, TextSegment, TextWriteOptions};
+use super::timecodes::{FrameTime, Timecodes};
+use super::timestamp::Timestamp;
+
+/// Which events an operation applies to. Criteria left empty match every event.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventFilter {
+    /// Events starting at or after the first timestamp and before the second.
+    pub time_range: Option<(Timestamp, Timestamp)>,
+    /// Events in any of these styles.
+    pub styles: Vec<String>,
+    /// Events whose `name` is any of these.
+    pub actors: Vec<String>,
+    /// Events on any of these layers.
+    pub layers: Vec<i64>,
+    /// Only dialogues or only comments.
+    pub event_type: Option<EventType>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Dialogue) -> bool {
+        self.time_range
+            .is_none_or(|(from, to)| from <= event.start && event.start < to)
+            && (self.styles.is_empty() || self.styles.contains(&event.style))
+            && (self.actors.is_empty() || self.actors.contains(&event.name))
+            && (self.layers.is_empty() || self.layers.contains(&event.layer))
+            && self.event_type.as_ref().is_none_or(|t| *t == event.type_)
+    }
+}
+
+/// How far, in frames, `SubtitlesFile::snap_to_keyframes` moves a start or an end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyframeThresholds {
+    /// Starts this many frames or fewer before a keyframe move to it.
+    pub starts_before: i64,
+    /// Starts this many frames or fewer after a keyframe move to it.
+    pub starts_after: i64,
+    /// Ends this many frames or fewer before the frame before a keyframe move to it.
+    pub ends_before: i64,
+    /// Ends this many frames or fewer after the frame before a keyframe move to it.
+    pub ends_after: i64,
+}
+
+impl Default for KeyframeThresholds {
+    fn default() -> Self {
+        Self {
+            starts_before: 5,
+            starts_after: 4,
+            ends_before: 5,
+            ends_after: 6,
+        }
+    }
+}
+
+/// What `SubtitlesFile::post_process_timing` does, with times in milliseconds. Zero
+/// turns a step off.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingPostProcessor {
+    /// Added before every line.
+    pub lead_in: i64,
+    /// Added after every line.
+    pub lead_out: i64,
+    /// Gaps up to this long between a line and the next one are closed.
+    pub max_gap: i64,
+    /// Overlaps up to this long between a line and the next one are removed.
+    pub max_overlap: i64,
+    /// Where chained lines meet, from 0.0 at the end of the first one to 1.0 at the start
+    /// of the second one.
+    pub chain_bias: f64,
+    /// The styles of the lines to change, all when empty.
+    pub styles: Vec<String>,
+}
+
+impl Default for TimingPostProcessor {
+    fn default() -> Self {
+        Self {
+            lead_in: 0,
+            lead_out: 0,
+            max_gap: 0,
+            max_overlap: 0,
+            chain_bias: 0.5,
+            styles: vec![],
+        }
+    }
+}
+
+impl SubtitlesFile {
+    /// Moves the events matching `filter` by `offset_ms` milliseconds, earlier when negative.
+    ///
+    /// Times stop at `0:00:00.00`. When a start is clamped, the times of `\t`, `\move`,
+    /// `\fad`, `\fade` and karaoke, which count from the start, are moved too so the line
+    /// looks the same as it would have from zero on.
+    pub fn shift(&mut self, offset_ms: i64, filter: &EventFilter) {
+        for event in self.events.iter_mut().filter(|e| filter.matches(e)) {
+            let duration = event.end.as_millis() as i64 - event.start.as_millis() as i64;
+            let start = event.start.as_millis() as i64 + offset_ms;
+            let end = event.end.as_millis() as i64 + offset_ms;
+            event.start = Timestamp::from_millis(start.max(0) as u64);
+            event.end = Timestamp::from_millis(end.max(0) as u64);
+            if start < 0 {
+                let cut = i32::try_from(-start).unwrap_or(i32::MAX);
+                cut_start(event, cut, i32::try_from(duration).unwrap_or(i32::MAX));
+            }
+        }
+    }
+
+    /// Converts the timing from `from` to `to` frames per second, keeping every event on the
+    /// same frames, e.g. between a 25 fps PAL release and the 23.976 fps one it was sped up
+    /// from.
+    ///
+    /// An event is shown on the frames from the first one at or after its start to the last
+    /// one before its end. New times land halfway between two frames, so rounding them to
+    /// centiseconds can't move them to another frame. The times of `\t`, `\move`, `\fad`,
+    /// `\fade` and karaoke are converted the same way, from the frame they fall on.
+    ///
+    /// # Panics
+    /// If `from` or `to` isn't a positive number.
+    pub fn convert_framerate(&mut self, from: f64, to: f64) {
+        for fps in [from, to] {
+            assert!(fps.is_finite() && fps > 0.0, "frame rate must be positive");
+        }
+        let convert = |ms: f64| {
+            // The epsilon keeps times exactly on a frame on that frame despite float error.
+            let frame = (ms * from / 1000.0 - 1e-6).ceil();
+            (frame - 0.5) * 1000.0 / to
+        };
+        let to_timestamp = |ms: f64| Timestamp::from_centis((ms / 10.0).round().max(0.0) as u32);
+        for event in &mut self.events {
+            let (start, end) = (event.start.as_millis() as f64, event.end.as_millis() as f64);
+            let new_start = to_timestamp(convert(start));
+            let new_end = to_timestamp(convert(end)).max(new_start);
+            convert_tag_times(event, start, end, convert);
+            event.start = new_start;
+            event.end = new_end;
+        }
+    }
+
+    /// Moves the `start` and `end` of the events matching `filter` halfway between frames,
+    /// as Aegisub does, keeping them on the frames they are shown on. An event shown on no
+    /// frame ends when it starts.
+    pub fn snap_to_frames(&mut self, timecodes: &Timecodes, filter: &EventFilter) {
+        let to_timestamp = |ms: i64| Timestamp::from_millis(ms.max(0) as u64);
+        for event in self.events.iter_mut().filter(|e| filter.matches(e)) {
+            let start = timecodes.time_at(event.start_frame(timecodes), FrameTime::Start);
+            let end = timecodes.time_at(event.end_frame(timecodes), FrameTime::End);
+            event.start = to_timestamp(start);
+            event.end = to_timestamp(end).max(event.start);
+        }
+    }
+
+    /// Adds lead-in and lead-out to dialogue lines, then chains each one to the next one
+    /// in time, like Aegisub's timing post-processor. Comments are left alone, and so are
+    /// tag times, which keep counting from the new start.
+    pub fn post_process_timing(&mut self, settings: &TimingPostProcessor) {
+        let filter = EventFilter {
+            styles: settings.styles.clone(),
+            event_type: Some(EventType::Dialogue),
+            ..EventFilter::default()
+        };
+        let mut lines: Vec<&mut Dialogue> = self
+            .events
+            .iter_mut()
+            .filter(|e| filter.matches(e))
+            .collect();
+        for line in lines.iter_mut() {
+            let start = line.start.as_millis() as i64 - settings.lead_in;
+            line.start = Timestamp::from_millis(start.max(0) as u64);
+            let end = line.end.as_millis() as i64 + settings.lead_out;
+            line.end = Timestamp::from_millis(end.max(0) as u64);
+        }
+
+        lines.sort_by_key(|line| line.start);
+        for i in 1..lines.len() {
+            let (prev, cur) = (&lines[i - 1], &lines[i]);
+            let (end, start) = (prev.end.as_millis() as i64, cur.start.as_millis() as i64);
+            let distance = start - end;
+            let chain = (distance > 0 && distance <= settings.max_gap)
+                || (distance < 0 && -distance <= settings.max_overlap);
+            // A line ending before the previous one does isn't the next one.
+            if chain && cur.end > prev.end {
+                let meet = (end as f64 + distance as f64 * settings.chain_bias).round();
+                let meet = Timestamp::from_millis(meet as u64);
+                lines[i - 1].end = meet;
+                lines[i].start = meet;
+            }
+        }
+    }
+
+    /// Moves the events matching `filter` to the nearest keyframe, like Aegisub's timing
+    /// post-processor: a start to the keyframe, an end to the frame before it, each only
+    /// when within `thresholds`.
+    pub fn snap_to_keyframes(
+        &mut self,
+        keyframes: &Keyframes,
+        timecodes: &Timecodes,
+        thresholds: &KeyframeThresholds,
+        filter: &EventFilter,
+    ) {
+        let to_timestamp = |ms: i64| Timestamp::from_millis(ms.max(0) as u64);
+        for event in self.events.iter_mut().filter(|e| filter.matches(e)) {
+            let start = event.start_frame(timecodes);
+            let start_range = start - thresholds.starts_after..=start + thresholds.starts_before;
+            if let Some(key) = keyframes
+                .closest_within(start, start_range)
+                .filter(|key| *key != start)
+            {
+                event.start = to_timestamp(timecodes.time_at(key, FrameTime::Start));
+            }
+
+            let end = event.end_frame(timecodes);
+            let next = end + 1;
+            let next_range = next - thresholds.ends_after..=next + thresholds.ends_before;
+            if let Some(last) = keyframes
+                .closest_within(next, next_range)
+                .map(|key| key - 1)
+                .filter(|last| *last != end)
+            {
+                event.end = to_timestamp(timecodes.time_at(last, FrameTime::End));
+            }
+            event.end = event.end.max(event.start);
+        }
+    }
+}
+
+/// Rewrites the tag times of a line from `start` to `end` milliseconds, mapping the
+/// absolute time of each through `convert`.
+///
+/// They are taken relative to the converted times before rounding, so that zero stays zero;
+/// the rounding is too small to move a time halfway between frames to another frame.
+fn convert_tag_times(event: &mut Dialogue, start: f64, end: f64, convert: impl Fn(f64) -> f64) {
+    let (new_start, new_end) = (convert(start), convert(end));
+    let relative = |t: i32| (convert(start + f64::from(t)) - new_start).round() as i32;
+    let mut segments = event.parse_text();
+    // Karaoke durations are in centiseconds and add up from the start.
+    let (mut karaoke, mut new_karaoke) = (0.0, 0.0);
+    let mut changed = false;
+    for segment in &mut segments {
+        let TextSegment::Override(items) = segment else {
+            continue;
+        };
+        for item in items {
+            let OverrideItem::Tag(tag) = item else {
+                continue;
+            };
+            changed |= match tag {
+                Tag::Move {
+                    times: Some((t1, t2)),
+                    ..
+                }
+                | Tag::Transform {
+                    times: Some((t1, t2)),
+                    ..
+                } => {
+                    (*t1, *t2) = (relative(*t1), relative(*t2));
+                    true
+                }
+                Tag::Fade { fade_in, fade_out } => {
+                    *fade_in = relative(*fade_in);
+                    let fade_start = convert(end - f64::from(*fade_out));
+                    *fade_out = (new_end - fade_start).round() as i32;
+                    true
+                }
+                Tag::ComplexFade { times, .. } => {
+                    times.iter_mut().for_each(|t| *t = relative(*t));
+                    true
+                }
+                Tag::Karaoke(_, length) => {
+                    karaoke += *length;
+                    let boundary = (convert(start + karaoke * 10.0) - new_start) / 10.0;
+                    *length = boundary.round() - new_karaoke;
+                    new_karaoke += *length;
+                    true
+                }
+                _ => false,
+            };
+        }
+    }
+    if changed {
+        event.text = write_text(&segments, &TextWriteOptions::default());
+    }
+}
+
+/// Rewrites the tag times of a line whose first `cut` milliseconds were dropped.
+///
+/// A `\move` or `\t` that ended before the new start is replaced by its final state, and
+/// one that was under way starts again from zero.
+fn cut_start(event: &mut Dialogue, cut: i32, duration: i32) {
+    let mut segments = event.parse_text();
+    // Karaoke durations are in centiseconds.
+    let mut karaoke_cut = f64::from(cut) / 10.0;
+    let shifted = |(t1, t2): (i32, i32)| (t1.saturating_sub(cut), t2.saturating_sub(cut));
+    let mut changed = false;
+    for segment in &mut segments {
+        let TextSegment::Override(items) = segment else {
+            continue;
+        };
+        let mut rewritten = Vec::with_capacity(items.len());
+        for item in items.drain(..) {
+            let OverrideItem::Tag(mut tag) = item else {
+                rewritten.push(item);
+                continue;
+            };
+            changed |= match &mut tag {
+                Tag::Move {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    times,
+                } => {
+                    // Like libass: reversed times are swapped and two zeros mean the whole line.
+                    let (t1, t2) = match *times {
+                        Some((t1, t2)) if t1 > 0 || t2 > 0 => (t1.min(t2), t1.max(t2)),
+                        _ => (0, duration),
+                    };
+                    let (new_t1, new_t2) = shifted((t1, t2));
+                    if new_t2 <= 0 {
+                        tag = Tag::Position { x: *x2, y: *y2 };
+                    } else {
+                        if new_t1 < 0 {
+                            let done = f64::from(cut - t1) / f64::from(t2 - t1);
+                            *x1 += (*x2 - *x1) * done;
+                            *y1 += (*y2 - *y1) * done;
+                        }
+                        *times = Some((new_t1.max(0), new_t2));
+                    }
+                    true
+                }
+                Tag::Transform { times, tags, .. } => {
+                    // A `\t` end of 0 is the end of the line.
+                    let (t1, t2) = match *times {
+                        Some((t1, 0)) => (t1, duration),
+                        Some(times) => times,
+                        None => (0, duration),
+                    };
+                    let (new_t1, new_t2) = shifted((t1, t2));
+                    if new_t2 <= 0 {
+                        rewritten.extend(std::mem::take(tags).into_iter().map(OverrideItem::Tag));
+                        changed = true;
+                        continue;
+                    }
+                    *times = Some((new_t1.clamp(0, new_t2 - 1), new_t2));
+                    true
+                }
+                Tag::Fade { fade_in, .. } => {
+                    *fade_in = (*fade_in - cut).max(0);
+                    true
+                }
+                Tag::ComplexFade { times, .. } => {
+                    times.iter_mut().for_each(|t| *t = (*t - cut).max(0));
+                    true
+                }
+                Tag::Karaoke(_, length) if karaoke_cut > 0.0 => {
+                    let eaten = length.min(karaoke_cut);
+                    *length -= eaten;
+                    karaoke_cut -= eaten;
+                    true
+                }
+                _ => false,
+            };
+            rewritten.push(OverrideItem::Tag(tag));
+        }
+        *items = rewritten;
+    }
+    if changed {
+        event.text = write_text(&segments, &TextWriteOptions::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventFilter, TimingPostProcessor};
+    use crate::prelude::{Dialogue, EventType, SubtitlesFile, Timestamp};
+
+    #[test]
+    fn test_shift() {
+        let mut file = SubtitlesFile {
+            events: vec![
+                Dialogue {
+                    start: Timestamp::new(0, 0, 1, 0),
+                    end: Timestamp::new(0, 0, 4, 0),
+                    text: r"{\fad(500,200)\move(0,0,30,30)\k150}a{\k100}b".to_owned(),
+                    ..Dialogue::default()
+                },
+                Dialogue {
+                    start: Timestamp::new(0, 0, 5, 0),
+                    end: Timestamp::new(0, 0, 6, 0),
+                    name: "Alice".to_owned(),
+                    ..Dialogue::default()
+                },
+                Dialogue {
+                    type_: EventType::Comment,
+                    ..Dialogue::default()
+                },
+            ],
+            ..SubtitlesFile::default()
+        };
+
+        let alice = EventFilter {
+            actors: vec!["Alice".to_owned()],
+            ..EventFilter::default()
+        };
+        file.shift(1500, &alice);
+        assert_eq!(file.events[1].start, Timestamp::new(0, 0, 6, 50));
+        assert_eq!(file.events[0].start, Timestamp::new(0, 0, 1, 0));
+
+        let dialogues = EventFilter {
+            event_type: Some(EventType::Dialogue),
+            ..EventFilter::default()
+        };
+        file.shift(-2000, &dialogues);
+        assert_eq!(file.events[0].start, Timestamp::ZERO);
+        assert_eq!(file.events[0].end, Timestamp::new(0, 0, 2, 0));
+        assert_eq!(
+            file.events[0].text,
+            r"{\fad(0,200)\move(10,10,30,30,0,2000)\k50}a{\k100}b"
+        );
+        assert_eq!(file.events[1].start, Timestamp::new(0, 0, 4, 50));
+        assert_eq!(file.events[2].end, Timestamp::new(0, 0, 5, 0));
+
+        // A move or transform that was over by the new start stays over.
+        let mut file = SubtitlesFile {
+            events: vec![Dialogue {
+                start: Timestamp::new(0, 0, 1, 0),
+                end: Timestamp::new(0, 0, 5, 0),
+                text: r"{\move(0,0,100,100,0,1000)\t(0,500,\fs80\bord2)\t(500,2000,\blur3)}a"
+                    .to_owned(),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        };
+        file.shift(-2000, &EventFilter::default());
+        assert_eq!(
+            file.events[0].text,
+            r"{\pos(100,100)\fs80\bord2\t(0,1000,\blur3)}a"
+        );
+    }
+
+    #[test]
+    fn test_post_process_timing() {
+        let line = |style: &str, start, end| Dialogue {
+            style: style.to_owned(),
+            start: Timestamp::from_centis(start),
+            end: Timestamp::from_centis(end),
+            ..Dialogue::default()
+        };
+        let mut file = SubtitlesFile {
+            events: vec![
+                line("Default", 300, 400),
+                line("Default", 100, 280),
+                line("Default", 520, 600),
+                line("Signs", 100, 150),
+                Dialogue {
+                    type_: EventType::Comment,
+                    ..line("Default", 200, 300)
+                },
+            ],
+            ..SubtitlesFile::default()
+        };
+        file.post_process_timing(&TimingPostProcessor {
+            lead_in: 100,
+            lead_out: 200,
+            max_gap: 1000,
+            max_overlap: 200,
+            styles: vec!["Default".to_owned()],
+            ..TimingPostProcessor::default()
+        });
+        let times: Vec<_> = file
+            .events
+            .iter()
+            .map(|e| (e.start.as_centis(), e.end.as_centis()))
+            .collect();
+        // With lead-in and lead-out, 0:00.90-0:03.00 and 0:02.90-0:04.20 overlap by 100 ms
+        // and 0:05.10-0:06.20 starts 900 ms after.
+        assert_eq!(
+            times,
+            [(295, 465), (90, 295), (465, 620), (100, 150), (200, 300)]
+        );
+    }
+
+    #[test]
+    fn test_convert_framerate() {
+        let mut file = SubtitlesFile {
+            events: vec![Dialogue {
+                start: Timestamp::new(0, 0, 1, 0),
+                end: Timestamp::new(0, 0, 2, 0),
+                text: r"{\fad(200,0)\t(0,400,\frz10)\k50}a{\k50}b".to_owned(),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        };
+        file.convert_framerate(25.0, 24000.0 / 1001.0);
+        let event = &file.events[0];
+        assert_eq!(event.start, Timestamp::new(0, 0, 1, 2));
+        assert_eq!(event.end, Timestamp::new(0, 0, 2, 6));
+        assert_eq!(event.text, r"{\fad(209,0)\t(0,417,\frz10)\k54}a{\k50}b");
+
+        // Going back lands on the same frames.
+        file.convert_framerate(24000.0 / 1001.0, 25.0);
+        let event = &file.events[0];
+        assert_eq!(event.start, Timestamp::new(0, 0, 0, 98));
+        assert_eq!(event.end, Timestamp::new(0, 0, 1, 98));
+    }
+
+    #[test]
+    #[should_panic(expected = "frame rate must be positive")]
+    fn test_convert_framerate_rejects_zero() {
+        SubtitlesFile::default().convert_framerate(25.0, 0.0);
+    }
+}
@@ -0,0 +1,295 @@
+use super::{default_style, escape_text, script_info, warn_lossy, warn_margins, ConversionWarning};
+use crate::parsers::ParseError;
+use crate::prelude::{
+    Colour, Dialogue, EventType, FrameTime, Styles, SubtitlesFile, Tag, TextSegment, Timecodes,
+    Timestamp,
+};
+use std::fmt::Write;
+
+impl SubtitlesFile {
+    /// Reads a MicroDVD file, whose frame numbers `timecodes` turns into times unless its
+    /// first line gives a frame rate, as `{1}{1}23.976` does. `Timecodes::constant` makes
+    /// timecodes of a frame rate.
+    ///
+    /// `|` breaks lines, a `/` starting a line makes it italic and the control codes
+    /// `{y:...}`, `{c:$BBGGRR}`, `{f:...}` and `{s:...}` become override tags, for the line
+    /// they start when lowercase and for the whole subtitle when uppercase. Other control
+    /// codes, such as `{P:...}`, are left out.
+    pub fn from_microdvd(input: &str, timecodes: &Timecodes) -> Result<Self, ParseError> {
+        let mut declared = None;
+        let mut events = vec![];
+        for line in input.strip_prefix('\u{feff}').unwrap_or(input).lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (start, end, text) = frames(input, line)?;
+            if events.is_empty() && declared.is_none() && (start, end) == (1, 1) {
+                if let Some(fps) = text.trim().parse::<f64>().ok().filter(|fps| *fps > 0.0) {
+                    declared = Some(Timecodes::constant(fps));
+                    continue;
+                }
+            }
+            let timecodes = declared.as_ref().unwrap_or(timecodes);
+            let time =
+                |frame, kind| Timestamp::from_millis(timecodes.time_at(frame, kind).max(0) as u64);
+            events.push(Dialogue {
+                start: time(start, FrameTime::Start),
+                end: time(end, FrameTime::End),
+                text: microdvd_to_ass(text),
+                ..Dialogue::default()
+            });
+        }
+
+        Ok(Self {
+            script_info: script_info(),
+            v4styles: vec![default_style()],
+            events,
+            ..Self::default()
+        })
+    }
+
+    /// Writes the dialogue lines as MicroDVD subtitles, in order of start time, the first
+    /// line giving the frame rate of constant `timecodes`.
+    ///
+    /// Bold, italic, underline, strike-out and the primary colour of the first text of
+    /// each line, whether from the style or from tags, become `{y:...}` and `{c:$BBGGRR}`
+    /// control codes. What MicroDVD can't express is left out and reported.
+    pub fn to_microdvd(&self, timecodes: &Timecodes) -> (String, Vec<ConversionWarning>) {
+        let mut out = String::new();
+        if let Some(fps) = timecodes.constant_fps() {
+            let _ = writeln!(out, "{{1}}{{1}}{}", (fps * 1000.0).round() / 1000.0);
+        }
+        let mut warnings = vec![];
+        let mut events: Vec<_> = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.type_ == EventType::Dialogue)
+            .collect();
+        events.sort_by_key(|(_, event)| event.start);
+        for (index, event) in events {
+            warn_margins(index, event, &mut warnings);
+            warn_lossy(index, event, microdvd_supports, &mut warnings);
+            let _ = writeln!(
+                out,
+                "{{{}}}{{{}}}{}",
+                event.start_frame(timecodes),
+                event.end_frame(timecodes),
+                ass_to_microdvd(event, &self.v4styles)
+            );
+        }
+        (out, warnings)
+    }
+}
+
+fn microdvd_supports(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Bold(_)
+            | Tag::Italic(_)
+            | Tag::Underline(_)
+            | Tag::StrikeOut(_)
+            | Tag::Colour(1, _)
+            | Tag::Reset(_)
+            | Tag::DrawingMode(_)
+    )
+}
+
+/// `{start}{end}text`, with frame numbers.
+fn frames<'a>(input: &str, line: &'a str) -> Result<(i64, i64, &'a str), ParseError> {
+    let invalid = || ParseError::unexpected_line(input, line);
+    let (start, rest) = line
+        .trim_start()
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .ok_or_else(invalid)?;
+    let (end, text) = rest
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .ok_or_else(invalid)?;
+    let frame = |value: &str, field| {
+        value
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| ParseError::invalid_number(input, value, field))
+    };
+    Ok((frame(start, "start frame")?, frame(end, "end frame")?, text))
+}
+
+/// Turns the control codes and `|` line breaks of MicroDVD text into ASS.
+fn microdvd_to_ass(text: &str) -> String {
+    let block = |tags: &[Tag]| match tags.is_empty() {
+        true => String::new(),
+        false => format!(
+            "{{{}}}",
+            tags.iter().map(Tag::to_string).collect::<String>()
+        ),
+    };
+    let mut subtitle_tags = vec![];
+    let mut lines = vec![];
+    for line in text.split('|') {
+        let mut rest = line;
+        // The tags of this line, and those undoing them at its end.
+        let (mut tags, mut undo) = (vec![], vec![]);
+        loop {
+            if let Some(after) = rest.strip_prefix('/') {
+                tags.push(Tag::Italic(Some(true)));
+                undo.push(Tag::Italic(Some(false)));
+                rest = after;
+                continue;
+            }
+            let Some((code, after)) = rest.strip_prefix('{').and_then(|r| r.split_once('}')) else {
+                break;
+            };
+            let Some((kind, value)) = code.split_once(':') else {
+                break;
+            };
+            let whole = kind.chars().all(|c| c.is_ascii_uppercase());
+            let code_tags: Vec<(Tag, Tag)> = match kind.to_ascii_lowercase().as_str() {
+                "y" => value
+                    .chars()
+                    .filter_map(|c| match c.to_ascii_lowercase() {
+                        'b' => Some((Tag::Bold(Some(1)), Tag::Bold(Some(0)))),
+                        'i' => Some((Tag::Italic(Some(true)), Tag::Italic(Some(false)))),
+                        'u' => Some((Tag::Underline(Some(true)), Tag::Underline(Some(false)))),
+                        's' => Some((Tag::StrikeOut(Some(true)), Tag::StrikeOut(Some(false)))),
+                        _ => None,
+                    })
+                    .collect(),
+                "c" => u32::from_str_radix(value.trim().trim_start_matches('$'), 16)
+                    .map(|bgr| {
+                        (
+                            Tag::Colour(1, Some(Colour::from_abgr(bgr & 0xFF_FFFF))),
+                            Tag::Colour(1, None),
+                        )
+                    })
+                    .into_iter()
+                    .collect(),
+                "f" => vec![(Tag::FontName(Some(value.to_owned())), Tag::FontName(None))],
+                "s" => value
+                    .trim()
+                    .parse()
+                    .map(|size| (Tag::FontSize(Some(size)), Tag::FontSize(None)))
+                    .into_iter()
+                    .collect(),
+                _ => vec![],
+            };
+            for (tag, undo_tag) in code_tags {
+                match whole {
+                    true => subtitle_tags.push(tag),
+                    false => {
+                        tags.push(tag);
+                        undo.push(undo_tag);
+                    }
+                }
+            }
+            rest = after;
+        }
+        lines.push((block(&tags) + &escape_text(rest), block(&undo)));
+    }
+    let count = lines.len();
+    let lines: Vec<String> = lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, (line, undo))| match i + 1 < count {
+            true => line + &undo,
+            false => line,
+        })
+        .collect();
+    block(&subtitle_tags) + &lines.join("\\N")
+}
+
+/// The text of `event` with `|` line breaks and control codes for the formatting of the
+/// first text of each line.
+fn ass_to_microdvd(event: &Dialogue, styles: &[Styles]) -> String {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut codes = None;
+    let mut end_line = |line: &mut String, codes: &mut Option<String>| {
+        lines.push(codes.take().unwrap_or_default() + line);
+        line.clear();
+    };
+    for run in event.styled_runs(styles) {
+        let text = match &run.segment {
+            TextSegment::Text(text) => text.as_str(),
+            TextSegment::SoftBreak => " ",
+            TextSegment::HardSpace => "\u{a0}",
+            TextSegment::HardBreak => {
+                end_line(&mut line, &mut codes);
+                continue;
+            }
+            TextSegment::Override(_) | TextSegment::Drawing(_) => continue,
+        };
+        if codes.is_none() {
+            let style = &run.style;
+            let mut line_codes = String::new();
+            let y: String = [
+                (style.bold, 'b'),
+                (style.italic, 'i'),
+                (style.underline, 'u'),
+                (style.strikeout, 's'),
+            ]
+            .into_iter()
+            .filter_map(|(on, code)| on.then_some(code))
+            .collect();
+            if !y.is_empty() {
+                let _ = write!(line_codes, "{{y:{}}}", y);
+            }
+            let Colour { r, g, b, .. } = style.primary_colour;
+            if (r, g, b) != (0xFF, 0xFF, 0xFF) {
+                let _ = write!(line_codes, "{{c:${:02X}{:02X}{:02X}}}", b, g, r);
+            }
+            codes = Some(line_codes);
+        }
+        line += text;
+    }
+    end_line(&mut line, &mut codes);
+    lines.join("|")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{ConversionWarning, ParseError, SubtitlesFile, Timecodes, Timestamp};
+
+    #[test]
+    fn test_microdvd() {
+        let sub = "{1}{1}25\n{25}{50}{Y:b}Hello|{c:$0000FF}red|/slanted\n\
+            {100}{149}{P:0,0}{y:iu}Bye\n";
+        let file = SubtitlesFile::from_microdvd(sub, &Timecodes::constant(30.0)).unwrap();
+        assert_eq!(file.events.len(), 2);
+        let event = &file.events[0];
+        assert_eq!(event.start, Timestamp::new(0, 0, 0, 98));
+        assert_eq!(event.end, Timestamp::new(0, 0, 2, 2));
+        assert_eq!(
+            event.text,
+            r"{\b1}Hello\N{\c&H0000FF&}red{\c}\N{\i1}slanted"
+        );
+        assert_eq!(file.events[1].text, r"{\i1\u1}Bye");
+
+        let (sub, warnings) = file.to_microdvd(&Timecodes::constant(25.0));
+        assert!(warnings.is_empty());
+        assert_eq!(
+            sub,
+            "{1}{1}25\n{25}{50}{y:b}Hello|{y:b}{c:$0000FF}red|{y:bi}slanted\n\
+            {100}{149}{y:iu}Bye\n"
+        );
+        let again = SubtitlesFile::from_microdvd(&sub, &Timecodes::constant(30.0)).unwrap();
+        assert_eq!(again.events[1], file.events[1]);
+
+        let escaped =
+            SubtitlesFile::from_microdvd("{1}{25}C:\\New {note}\n", &Timecodes::constant(25.0))
+                .unwrap();
+        assert_eq!(escaped.events[0].text, "C:\\\u{2060}New \\{note}");
+
+        let mut file = file;
+        file.events[1].text = r"{\pos(1,1)\fs20}Bye".to_owned();
+        let (_, warnings) = file.to_microdvd(&Timecodes::constant(25.0));
+        assert_eq!(warnings[0], ConversionWarning::Positioning { event: 1 });
+        assert_eq!(warnings.len(), 2);
+
+        assert!(matches!(
+            SubtitlesFile::from_microdvd("{1}{x}a\n", &Timecodes::constant(25.0)),
+            Err(ParseError::InvalidNumber { line: 1, .. })
+        ));
+    }
+}
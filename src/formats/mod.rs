@@ -0,0 +1,231 @@
+use crate::prelude::{
+    Dialogue, OverrideItem, ResolvedStyle, ScriptInfo, StyledRun, Styles, Tag, TextSegment,
+    Timestamp,
+};
+
+mod microdvd;
+mod srt;
+mod ssa;
+mod subviewer;
+mod ttml;
+mod webvtt;
+
+/// Something of an event that another format can't express, dropped when converting to it.
+/// `event` is the index of the event in `SubtitlesFile::events`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConversionWarning {
+    /// `\pos`, `\move`, `\org`, a clip or margins.
+    Positioning { event: usize },
+    /// Karaoke timing.
+    Karaoke { event: usize },
+    /// A `\p` drawing, left out of the text.
+    Drawing { event: usize },
+    /// Any other override tag without an equivalent, as written in the script.
+    UnsupportedTag { event: usize, tag: String },
+}
+
+/// Adds to `warnings` what of `event`, the event at `index`, a format can't express, but
+/// for the override tags it `supports`. Each warning is only added once per event.
+pub(crate) fn warn_lossy(
+    index: usize,
+    event: &Dialogue,
+    supports: impl Fn(&Tag) -> bool,
+    warnings: &mut Vec<ConversionWarning>,
+) {
+    let mut found = vec![];
+    for segment in event.parse_text() {
+        let tags = match segment {
+            TextSegment::Override(items) => items,
+            TextSegment::Drawing(_) => {
+                found.push(ConversionWarning::Drawing { event: index });
+                continue;
+            }
+            _ => continue,
+        };
+        for item in tags {
+            let warning = match item {
+                OverrideItem::Tag(tag) if supports(&tag) => continue,
+                OverrideItem::Tag(
+                    Tag::Position { .. } | Tag::Move { .. } | Tag::Origin { .. } | Tag::Clip { .. },
+                ) => ConversionWarning::Positioning { event: index },
+                OverrideItem::Tag(Tag::Karaoke(..)) => ConversionWarning::Karaoke { event: index },
+                OverrideItem::Tag(tag) => ConversionWarning::UnsupportedTag {
+                    event: index,
+                    tag: tag.to_string(),
+                },
+                OverrideItem::Comment(_) => continue,
+            };
+            found.push(warning);
+        }
+    }
+    for warning in found {
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+}
+
+/// Adds a `Positioning` warning for `event`, the event at `index`, if it has margins,
+/// for formats without any placement.
+fn warn_margins(index: usize, event: &Dialogue, warnings: &mut Vec<ConversionWarning>) {
+    if event.margin_l != 0.0 || event.margin_r != 0.0 || event.margin_v != 0.0 {
+        warnings.push(ConversionWarning::Positioning { event: index });
+    }
+}
+
+/// The `[Script Info]` of an imported file, at the resolution renderers assume by default.
+fn script_info() -> ScriptInfo {
+    ScriptInfo {
+        scaled_border_and_shadow: true,
+        play_res_x: 384,
+        play_res_y: 288,
+        ..ScriptInfo::default()
+    }
+}
+
+/// The `Default` style of an imported file, sized for [`script_info`].
+fn default_style() -> Styles {
+    Styles {
+        font_size: 16.0,
+        outline: 1.0,
+        shadow: 0.0,
+        margin_l: 10.0,
+        margin_r: 10.0,
+        margin_v: 10.0,
+        ..Styles::default()
+    }
+}
+
+/// `HH:MM:SS` and milliseconds after `separator`, as SubRip, WebVTT and TTML write times.
+fn clock_time(time: Timestamp, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        time.hours(),
+        time.minutes(),
+        time.seconds(),
+        separator,
+        time.centis() * 10
+    )
+}
+
+/// A number with at most two decimals.
+fn decimal(n: f64) -> String {
+    let n = format!("{:.2}", n);
+    n.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+/// A style name as a CSS class or an XML id: anything but letters, digits, `-` and `_`
+/// becomes `_`, and a leading digit gets a `_` before it.
+fn identifier(style: &str) -> String {
+    let style = style.strip_prefix('*').unwrap_or(style);
+    let mut identifier = match style.starts_with(|c: char| c.is_ascii_digit()) {
+        true => String::from("_"),
+        false => String::new(),
+    };
+    identifier.extend(style.chars().map(|c| {
+        match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        }
+    }));
+    identifier
+}
+
+/// Escapes plain text read from another format for `Dialogue.text`: a `{` becomes `\{`,
+/// and a word joiner keeps a backslash from starting `\N`, `\n`, `\h` or `\{`.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => escaped.push_str("\\{"),
+            '\\' if matches!(chars.peek(), Some('N' | 'n' | 'h' | '{' | '}')) => {
+                escaped.push_str("\\\u{2060}")
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A piece of the text of a SubRip or WebVTT cue.
+enum Html<'a> {
+    Text(&'a str),
+    /// A tag without its angle brackets, e.g. `/i` or `font color="#FF0000"`.
+    Tag(&'a str),
+}
+
+/// Splits cue text into text and tags. A `<` without a matching `>` is text.
+fn split_html(mut html: &str) -> Vec<Html<'_>> {
+    let mut pieces = vec![];
+    while let Some((open, close)) = html
+        .find('<')
+        .and_then(|open| Some((open, open + html[open..].find('>')?)))
+    {
+        if open > 0 {
+            pieces.push(Html::Text(&html[..open]));
+        }
+        pieces.push(Html::Tag(&html[open + 1..close]));
+        html = &html[close + 1..];
+    }
+    if !html.is_empty() {
+        pieces.push(Html::Text(html));
+    }
+    pieces
+}
+
+/// Writes `runs` as cue text, opening the tags `wanted` by the style of each run and
+/// closing them, innermost first, once they no longer are. `text` writes the text itself.
+///
+/// A blank line would end the cue, so runs of line breaks and breaks at either end are
+/// dropped.
+fn write_html(
+    runs: &[StyledRun],
+    wanted: impl Fn(&ResolvedStyle) -> Vec<String>,
+    text: impl Fn(&mut String, &str),
+) -> String {
+    let mut html = String::new();
+    let mut open: Vec<String> = vec![];
+    let close = |html: &mut String, tag: &str| {
+        let name = tag[1..].split(['.', ' ', '>']).next().unwrap_or_default();
+        html.push_str(&format!("</{}>", name));
+    };
+    for run in runs {
+        let wanted = wanted(&run.style);
+        let keep = open.iter().take_while(|tag| wanted.contains(tag)).count();
+        for tag in open.drain(keep..).rev() {
+            close(&mut html, &tag);
+        }
+        for tag in wanted {
+            if !open.contains(&tag) {
+                html.push_str(&tag);
+                open.push(tag);
+            }
+        }
+        match &run.segment {
+            TextSegment::Text(run) => text(&mut html, run),
+            TextSegment::HardBreak if !is_blank(line(&html)) => html.push('\n'),
+            TextSegment::HardBreak => {}
+            TextSegment::SoftBreak => html.push(' '),
+            TextSegment::HardSpace => text(&mut html, "\u{a0}"),
+            TextSegment::Override(_) | TextSegment::Drawing(_) => {}
+        }
+    }
+    if let Some(last_break) = html.rfind('\n').filter(|_| is_blank(line(&html))) {
+        html.truncate(last_break);
+    }
+    for tag in open.iter().rev() {
+        close(&mut html, tag);
+    }
+    html
+}
+
+/// The last line of `html`.
+fn line(html: &str) -> &str {
+    html.rsplit('\n').next().unwrap_or_default()
+}
+
+/// Whether a cue reader would take `line` for the blank line ending a cue.
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
@@ -0,0 +1,266 @@
+use super::{
+    clock_time, default_style, escape_text, script_info, split_html, warn_lossy, warn_margins,
+    write_html, ConversionWarning, Html,
+};
+use crate::parsers::{clock_timestamp, ParseError};
+use crate::prelude::{
+    Alignment, Colour, Dialogue, EventType, ResolvedStyle, Styles, SubtitlesFile, Tag, Timestamp,
+};
+use std::fmt::Write;
+
+impl SubtitlesFile {
+    /// Reads a SubRip file into a script with a single `Default` style.
+    ///
+    /// `<b>`, `<i>`, `<u>`, `<s>` and `<font color>` become override tags, other HTML tags
+    /// are kept as text, and `{\an8}`-like tags, being ASS already, work as they are. Other
+    /// braces and backslashes stay text. Cue numbers are optional and times are rounded to
+    /// centiseconds.
+    pub fn from_srt(input: &str) -> Result<Self, ParseError> {
+        let end_of_input = &input[input.len()..];
+        let mut lines = input
+            .strip_prefix('\u{feff}')
+            .unwrap_or(input)
+            .lines()
+            .peekable();
+        let mut events = vec![];
+        while let Some(line) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let timing = match line.trim().bytes().all(|b| b.is_ascii_digit()) {
+                true => lines.next().unwrap_or(end_of_input),
+                false => line,
+            };
+            let (start, end) = cue_timing(input, timing)?;
+            let mut text = vec![];
+            while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+                text.push(line);
+            }
+            events.push(Dialogue {
+                start,
+                end,
+                text: html_to_ass(&text.join("\n")).replace('\n', "\\N"),
+                ..Dialogue::default()
+            });
+        }
+
+        Ok(Self {
+            script_info: script_info(),
+            v4styles: vec![default_style()],
+            events,
+            ..Self::default()
+        })
+    }
+
+    /// Writes the dialogue lines as SubRip cues, in order of start time.
+    ///
+    /// Bold, italic, underline, strike-out and the primary colour, whether from the style
+    /// or from tags, become HTML tags and the alignment a leading `{\an8}`-like tag. What
+    /// SubRip can't express is left out and reported.
+    pub fn to_srt(&self) -> (String, Vec<ConversionWarning>) {
+        let mut warnings = vec![];
+        let mut events: Vec<_> = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.type_ == EventType::Dialogue)
+            .collect();
+        events.sort_by_key(|(_, event)| event.start);
+
+        let mut out = String::new();
+        for (number, (index, event)) in events.into_iter().enumerate() {
+            warn_margins(index, event, &mut warnings);
+            warn_lossy(index, event, srt_supports, &mut warnings);
+            let _ = write!(
+                out,
+                "{}\n{} --> {}\n{}\n\n",
+                number + 1,
+                clock_time(event.start, ','),
+                clock_time(event.end, ','),
+                ass_to_html(event, &self.v4styles),
+            );
+        }
+        (out, warnings)
+    }
+}
+
+fn srt_supports(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Bold(_)
+            | Tag::Italic(_)
+            | Tag::Underline(_)
+            | Tag::StrikeOut(_)
+            | Tag::Colour(1, _)
+            | Tag::Alignment(_)
+            | Tag::LegacyAlignment(_)
+            | Tag::Reset(_)
+            | Tag::DrawingMode(_)
+    )
+}
+
+/// `00:00:01,000 --> 00:00:02,500`, maybe followed by a position which is ignored.
+fn cue_timing(input: &str, line: &str) -> Result<(Timestamp, Timestamp), ParseError> {
+    let Some((start, rest)) = line.split_once("-->") else {
+        return Err(ParseError::unexpected_line(input, line));
+    };
+    let end = rest.split_whitespace().next().unwrap_or(rest);
+    Ok((
+        clock_timestamp(input, start.trim(), "Start")?,
+        clock_timestamp(input, end, "End")?,
+    ))
+}
+
+/// Turns the HTML tags of the text of a cue into override tags.
+fn html_to_ass(html: &str) -> String {
+    let mut text = String::new();
+    // The colours of the `<font>` tags still open.
+    let mut fonts: Vec<Option<Colour>> = vec![];
+    for piece in split_html(html) {
+        let tag = match piece {
+            Html::Text(run) => {
+                push_text(&mut text, run);
+                continue;
+            }
+            Html::Tag(tag) => tag,
+        };
+        let name = tag.trim().to_ascii_lowercase();
+        let closing = name.starts_with('/');
+        let ass_tag = match name.split_whitespace().next().unwrap_or_default() {
+            "b" | "/b" => Some(Tag::Bold(Some(i32::from(!closing)))),
+            "i" | "/i" => Some(Tag::Italic(Some(!closing))),
+            "u" | "/u" => Some(Tag::Underline(Some(!closing))),
+            "s" | "/s" => Some(Tag::StrikeOut(Some(!closing))),
+            "font" => {
+                let colour = font_colour(tag);
+                fonts.push(colour);
+                colour.map(|colour| Tag::Colour(1, Some(colour)))
+            }
+            "/font" => fonts.pop().flatten().map(|_| {
+                let outer = fonts.iter().rev().find_map(|colour| *colour);
+                Tag::Colour(1, outer)
+            }),
+            _ => {
+                push_text(&mut text, &format!("<{}>", tag));
+                None
+            }
+        };
+        if let Some(tag) = ass_tag {
+            let _ = write!(text, "{{{}}}", tag);
+        }
+    }
+    text
+}
+
+/// Adds a run of cue text to `text`, escaped but for its `{\...}` override blocks.
+fn push_text(text: &mut String, mut run: &str) {
+    while let Some((open, close)) = run
+        .find("{\\")
+        .and_then(|open| Some((open, open + run[open..].find('}')?)))
+    {
+        text.push_str(&escape_text(&run[..open]));
+        text.push_str(&run[open..=close]);
+        run = &run[close + 1..];
+    }
+    text.push_str(&escape_text(run));
+}
+
+/// The `color` of a `<font>` tag, `#RRGGBB` with or without the `#`.
+fn font_colour(tag: &str) -> Option<Colour> {
+    let lower = tag.to_ascii_lowercase();
+    let value = &tag[lower.find("color")? + "color".len()..];
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let value = value.trim_start_matches(['"', '\'']);
+    let hex = value.trim_start_matches('#').get(..6)?;
+    Colour::from_hex(&format!("#{}", hex)).ok()
+}
+
+/// The text of `event` with its formatting as HTML tags.
+fn ass_to_html(event: &Dialogue, styles: &[Styles]) -> String {
+    let runs = event.styled_runs(styles);
+    let mut text = String::new();
+    if let Some(run) = runs.first() {
+        if run.style.alignment != Alignment::BottomCenter {
+            let _ = write!(text, "{{\\an{}}}", run.style.alignment.numpad());
+        }
+    }
+    let wanted = |style: &ResolvedStyle| {
+        let Colour { r, g, b, .. } = style.primary_colour;
+        [
+            ((r, g, b) != (0xFF, 0xFF, 0xFF))
+                .then(|| format!("<font color=\"#{:02X}{:02X}{:02X}\">", r, g, b)),
+            style.bold.then(|| "<b>".to_owned()),
+            style.italic.then(|| "<i>".to_owned()),
+            style.underline.then(|| "<u>".to_owned()),
+            style.strikeout.then(|| "<s>".to_owned()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    };
+    text + &write_html(&runs, wanted, |html, text| html.push_str(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{ConversionWarning, ParseError, SubtitlesFile, Timestamp};
+
+    #[test]
+    fn test_srt() {
+        let srt = "\u{feff}1\r\n00:00:01,234 --> 00:00:03,005 X1:10\r\n<i>Hello</i>\r\n\
+            <font color=\"#FF0000\">red <b>bold</b></font> <span>\r\n\r\n\
+            00:01:00.5 --> 00:01:02,000\r\n{\\an8}Top\r\n";
+        let file = SubtitlesFile::from_srt(srt).unwrap();
+        assert_eq!(file.events.len(), 2);
+        assert_eq!(file.events[0].start, Timestamp::new(0, 0, 1, 23));
+        assert_eq!(file.events[0].end, Timestamp::new(0, 0, 3, 1));
+        assert_eq!(
+            file.events[0].text,
+            r"{\i1}Hello{\i0}\N{\c&H0000FF&}red {\b1}bold{\b0}{\c} <span>"
+        );
+        assert_eq!(file.events[1].start, Timestamp::new(0, 1, 0, 50));
+
+        let (srt, warnings) = file.to_srt();
+        assert_eq!(
+            srt,
+            "1\n00:00:01,230 --> 00:00:03,010\n<i>Hello</i>\n\
+            <font color=\"#FF0000\">red <b>bold</b></font> <span>\n\n\
+            2\n00:01:00,500 --> 00:01:02,000\n{\\an8}Top\n\n"
+        );
+        assert!(warnings.is_empty());
+
+        let mut file = file;
+        file.events[0].text = r"\Na\N\N{\i1}b\N".to_owned();
+        let again = SubtitlesFile::from_srt(&file.to_srt().0).unwrap();
+        assert_eq!(again.events[0].text, r"a\N{\i1}b{\i0}");
+
+        file.events[1].text = r"{\pos(10,10)\k20\fs30}a{\k20\p1}m 0 0 l 1 1".to_owned();
+        let (_, warnings) = file.to_srt();
+        assert_eq!(
+            warnings,
+            [
+                ConversionWarning::Positioning { event: 1 },
+                ConversionWarning::Karaoke { event: 1 },
+                ConversionWarning::UnsupportedTag {
+                    event: 1,
+                    tag: r"\fs30".to_owned()
+                },
+                ConversionWarning::Drawing { event: 1 },
+            ]
+        );
+
+        let file =
+            SubtitlesFile::from_srt("1\n00:00:01,000 --> 00:00:02,000\n{\\an8}C:\\New {note}\n")
+                .unwrap();
+        assert_eq!(file.events[0].text, "{\\an8}C:\\\u{2060}New \\{note}");
+        assert_eq!(
+            file.events[0].plain_text(&file.script_info.wrap_style),
+            "C:\\\u{2060}New {note}"
+        );
+
+        assert!(matches!(
+            SubtitlesFile::from_srt("1\n00:00:01,000 -> 00:00:02,000\n"),
+            Err(ParseError::UnexpectedLine { line: 2, .. })
+        ));
+    }
+}
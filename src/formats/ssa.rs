@@ -0,0 +1,207 @@
+use super::{warn_lossy, ConversionWarning};
+use crate::document::document::{SSA_EVENT_FORMAT, SSA_STYLE_FORMAT};
+use crate::prelude::{
+    write_text, BorderStyle, Colour, Dialogue, KaraokeEffect, OverrideItem, ScriptInfo, Styles,
+    SubtitlesFile, Tag, TextSegment, TextWriteOptions,
+};
+use std::fmt::Write;
+
+impl SubtitlesFile {
+    /// Writes the document as an SSA v4 file, which `parse_file()` reads back.
+    ///
+    /// Alignments use the SSA numbering, `\an` tags becoming `\a` tags, and colours are
+    /// written as decimal numbers. Layers, the `[Aegisub Project Garbage]`, unknown columns
+    /// and the style columns SSA doesn't have (underline, strike-out, scaling, spacing and
+    /// angle) are left out; what the events' text can't express is reported.
+    pub fn to_ssa(&self) -> (String, Vec<ConversionWarning>) {
+        let script_info = ScriptInfo {
+            script_type: String::from("v4.00"),
+            ..self.script_info.clone()
+        };
+        let mut out = script_info.print();
+
+        let _ = writeln!(
+            out,
+            "\n[V4 Styles]\nFormat: {}",
+            SSA_STYLE_FORMAT.join(", ")
+        );
+        for style in &self.v4styles {
+            let _ = writeln!(out, "{}", style_line(style));
+        }
+
+        let mut warnings = vec![];
+        let _ = writeln!(out, "\n[Events]\nFormat: {}", SSA_EVENT_FORMAT.join(", "));
+        for (index, event) in self.events.iter().enumerate() {
+            warn_lossy(index, event, ssa_supports, &mut warnings);
+            let _ = writeln!(out, "{}", event_line(event));
+        }
+
+        for section in &self.extra_sections {
+            let _ = writeln!(out, "\n[{}]", section.name);
+            for line in &section.lines {
+                let _ = writeln!(out, "{}", line);
+            }
+        }
+        (out, warnings)
+    }
+}
+
+/// The tags of SSA v4, and `\an` which is written as `\a`. `\kf` and `\ko` are ASS.
+fn ssa_supports(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Bold(_)
+            | Tag::Italic(_)
+            | Tag::FontName(_)
+            | Tag::FontSize(_)
+            | Tag::FontEncoding(_)
+            | Tag::Colour(..)
+            | Tag::Alpha(_)
+            | Tag::ColourAlpha(..)
+            | Tag::Alignment(_)
+            | Tag::LegacyAlignment(_)
+            | Tag::Reset(_)
+            | Tag::Karaoke(KaraokeEffect::Instant, _)
+    )
+}
+
+/// SSA writes colours as the signed decimal form of `&HAABBGGRR`.
+fn decimal(colour: Colour) -> i32 {
+    colour.to_abgr() as i32
+}
+
+fn style_line(style: &Styles) -> String {
+    let bool = |value: bool| match value {
+        true => -1,
+        false => 0,
+    };
+    // SSA only knows outlines and opaque boxes.
+    let border_style = match style.border_style {
+        BorderStyle::Outline => 1,
+        BorderStyle::OpaqueBox | BorderStyle::BackgroundBox => 3,
+        BorderStyle::Other(border_style) => border_style,
+    };
+    format!(
+        "Style: {},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},0,{:?}",
+        style.name,
+        style.font_name,
+        style.font_size,
+        decimal(style.primary_colour),
+        decimal(style.secondary_colour),
+        decimal(style.outline_colour),
+        decimal(style.back_colour),
+        bool(style.bold),
+        bool(style.italic),
+        border_style,
+        style.outline,
+        style.shadow,
+        style.alignment.legacy(),
+        style.margin_l,
+        style.margin_r,
+        style.margin_v,
+        style.encoding,
+    )
+}
+
+fn event_line(event: &Dialogue) -> String {
+    format!(
+        "{:?}: Marked=0,{},{},{},{},{},{},{},{},{}",
+        event.type_,
+        event.start,
+        event.end,
+        event.style,
+        event.name,
+        event.margin_l.round(),
+        event.margin_r.round(),
+        event.margin_v.round(),
+        event.effect,
+        legacy_text(event),
+    )
+}
+
+/// The text of `event` with its `\an` tags turned into `\a` tags.
+fn legacy_text(event: &Dialogue) -> String {
+    let mut segments = event.parse_text();
+    let mut changed = false;
+    for segment in &mut segments {
+        let TextSegment::Override(items) = segment else {
+            continue;
+        };
+        for item in items {
+            if let OverrideItem::Tag(Tag::Alignment(alignment)) = item {
+                *item = OverrideItem::Tag(Tag::LegacyAlignment(*alignment));
+                changed = true;
+            }
+        }
+    }
+    match changed {
+        true => write_text(&segments, &TextWriteOptions::default()),
+        false => event.text.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{
+        parse_file, Alignment, Colour, ConversionWarning, Dialogue, Styles, SubtitlesFile,
+    };
+
+    #[test]
+    fn test_ssa() {
+        let ssa = "[Script Info]\nScriptType: v4.00\nPlayResX: 640\nPlayResY: 480\n\n\
+            [V4 Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, \
+            TertiaryColour, BackColour, Bold, Italic, BorderStyle, Outline, Shadow, Alignment, \
+            MarginL, MarginR, MarginV, AlphaLevel, Encoding\n\
+            Style: Default,Arial,20,16777215,65535,-2147483648,0,-1,0,1,2,0,6,30,30,10,0,0\n\n\
+            [Events]\nFormat: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, \
+            Effect, Text\n\
+            Dialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0000,0000,0000,,{\\a9}Hi\n";
+        let file = parse_file(ssa).unwrap();
+        assert_eq!(file.script_info.script_type, "v4.00+");
+        let style = &file.v4styles[0];
+        assert_eq!(style.alignment, Alignment::TopCenter);
+        assert_eq!(style.primary_colour, Colour::WHITE);
+        assert_eq!(style.secondary_colour, Colour::new(0xFF, 0xFF, 0, 0));
+        assert_eq!(style.outline_colour, Colour::new(0, 0, 0, 0x80));
+        assert!(style.bold);
+        assert!(style.extra_fields.is_empty());
+        assert!(file.events[0].extra_fields.is_empty());
+        assert_eq!(file.events[0].text, r"{\a9}Hi");
+
+        let (written, warnings) = file.to_ssa();
+        assert!(warnings.is_empty());
+        assert!(written.starts_with("[Script Info]\n"));
+        assert!(written.contains("ScriptType: v4.00\n"));
+        assert!(written.contains(
+            "Style: Default,Arial,20,16777215,65535,-2147483648,0,-1,0,1,2,0,6,30,30,10,0,0\n"
+        ));
+        assert!(
+            written.contains("Dialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\a9}Hi\n")
+        );
+        let again = parse_file(&written).unwrap();
+        assert_eq!(again.v4styles, file.v4styles);
+        assert_eq!(again.events, file.events);
+
+        let file = SubtitlesFile {
+            v4styles: vec![Styles {
+                alignment: Alignment::MiddleLeft,
+                ..Styles::default()
+            }],
+            events: vec![Dialogue {
+                text: r"{\an8\blur2}a".to_owned(),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        };
+        let (written, warnings) = file.to_ssa();
+        assert!(written.contains(",9,10,10,10,0,1\n"));
+        assert!(written.ends_with(",{\\a6\\blur2}a\n"));
+        assert_eq!(
+            warnings,
+            [ConversionWarning::UnsupportedTag {
+                event: 0,
+                tag: r"\blur2".to_owned()
+            }]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use super::{default_style, escape_text, script_info, warn_lossy, warn_margins, ConversionWarning};
+use crate::parsers::{clock_timestamp, ParseError};
+use crate::prelude::{Dialogue, EventType, SubtitlesFile, Tag, Timestamp};
+use std::fmt::Write;
+
+impl SubtitlesFile {
+    /// Reads a SubViewer 2 file. The `[TITLE]` of its header becomes the title, and
+    /// `[br]` a line break.
+    pub fn from_subviewer(input: &str) -> Result<Self, ParseError> {
+        let mut lines = input
+            .strip_prefix('\u{feff}')
+            .unwrap_or(input)
+            .lines()
+            .peekable();
+        let mut title = None;
+        while let Some(line) = lines.next_if(|line| line.trim().is_empty() || line.starts_with('['))
+        {
+            if let Some(value) = line.strip_prefix("[TITLE]") {
+                title = Some(value.trim().to_owned());
+            }
+        }
+        let mut file = read_cues(input, lines, |text| {
+            escape_text(text).replace("[br]", "\\N")
+        })?;
+        if let Some(title) = title {
+            file.script_info.title = title;
+        }
+        Ok(file)
+    }
+
+    /// Writes the dialogue lines as SubViewer 2 subtitles, in order of start time, without
+    /// any formatting. What SubViewer can't express is left out and reported.
+    pub fn to_subviewer(&self) -> (String, Vec<ConversionWarning>) {
+        let mut out = format!(
+            "[INFORMATION]\n[TITLE]{}\n[AUTHOR]\n[SOURCE]\n[PRG]\n[FILEPATH]\n[DELAY]0\n\
+            [CD TRACK]0\n[COMMENT]\n[END INFORMATION]\n[SUBTITLE]\n\
+            [COLF]&HFFFFFF,[STYLE]no,[SIZE]18,[FONT]Arial\n",
+            self.script_info.title
+        );
+        let time = |time: Timestamp| {
+            format!(
+                "{:02}:{:02}:{:02}.{:02}",
+                time.hours(),
+                time.minutes(),
+                time.seconds(),
+                time.centis()
+            )
+        };
+        let warnings = write_cues(self, &mut out, time, "[br]");
+        (out, warnings)
+    }
+
+    /// Reads a YouTube SBV file.
+    pub fn from_sbv(input: &str) -> Result<Self, ParseError> {
+        let lines = input
+            .strip_prefix('\u{feff}')
+            .unwrap_or(input)
+            .lines()
+            .peekable();
+        read_cues(input, lines, escape_text)
+    }
+
+    /// Writes the dialogue lines as a YouTube SBV file, in order of start time, without any
+    /// formatting. What SBV can't express is left out and reported.
+    pub fn to_sbv(&self) -> (String, Vec<ConversionWarning>) {
+        let mut out = String::new();
+        let time = |time: Timestamp| {
+            format!(
+                "{}:{:02}:{:02}.{:03}",
+                time.hours(),
+                time.minutes(),
+                time.seconds(),
+                time.centis() * 10
+            )
+        };
+        let warnings = write_cues(self, &mut out, time, "\n");
+        (out, warnings)
+    }
+}
+
+/// Reads `start,end` lines each followed by the text of the cue, up to a blank line.
+/// `text` converts each line of text.
+fn read_cues<'a>(
+    input: &str,
+    mut lines: std::iter::Peekable<impl Iterator<Item = &'a str>>,
+    text: impl Fn(&str) -> String,
+) -> Result<SubtitlesFile, ParseError> {
+    let mut events = vec![];
+    while let Some(line) = lines.next() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((start, end)) = line.split_once(',') else {
+            return Err(ParseError::unexpected_line(input, line));
+        };
+        let mut cue = vec![];
+        while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+            cue.push(text(line));
+        }
+        events.push(Dialogue {
+            start: clock_timestamp(input, start.trim(), "Start")?,
+            end: clock_timestamp(input, end.trim(), "End")?,
+            text: cue.join("\\N"),
+            ..Dialogue::default()
+        });
+    }
+
+    Ok(SubtitlesFile {
+        script_info: script_info(),
+        v4styles: vec![default_style()],
+        events,
+        ..SubtitlesFile::default()
+    })
+}
+
+/// Writes the dialogue lines of `file` as `start,end` lines, each followed by its plain
+/// text, its lines joined by `line_break`, and a blank line. Blank lines of the text, which
+/// would end the cue early, are left out.
+fn write_cues(
+    file: &SubtitlesFile,
+    out: &mut String,
+    time: impl Fn(Timestamp) -> String,
+    line_break: &str,
+) -> Vec<ConversionWarning> {
+    let mut warnings = vec![];
+    let mut events: Vec<_> = file
+        .events
+        .iter()
+        .enumerate()
+        .filter(|(_, event)| event.type_ == EventType::Dialogue)
+        .collect();
+    events.sort_by_key(|(_, event)| event.start);
+    for (index, event) in events {
+        warn_margins(index, event, &mut warnings);
+        warn_lossy(index, event, plain_supports, &mut warnings);
+        let text = event.plain_text(&file.script_info.wrap_style);
+        let lines: Vec<&str> = text
+            .split('\n')
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        let _ = write!(
+            out,
+            "{},{}\n{}\n\n",
+            time(event.start),
+            time(event.end),
+            lines.join(line_break)
+        );
+    }
+    warnings
+}
+
+/// The tags of a format without formatting that don't matter.
+fn plain_supports(tag: &Tag) -> bool {
+    matches!(tag, Tag::Reset(_) | Tag::DrawingMode(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{ConversionWarning, ParseError, SubtitlesFile, Timestamp};
+
+    #[test]
+    fn test_subviewer_and_sbv() {
+        let sub = "[INFORMATION]\n[TITLE]Pilot\n[AUTHOR]\n[END INFORMATION]\n[SUBTITLE]\n\
+            [COLF]&HFFFFFF,[STYLE]no,[SIZE]18,[FONT]Arial\n\
+            00:00:01.00,00:00:03.50\nHello[br]world\n\n00:01:00.00,00:01:02.00\nBye\n";
+        let file = SubtitlesFile::from_subviewer(sub).unwrap();
+        assert_eq!(file.script_info.title, "Pilot");
+        assert_eq!(file.events.len(), 2);
+        assert_eq!(file.events[0].start, Timestamp::new(0, 0, 1, 0));
+        assert_eq!(file.events[0].end, Timestamp::new(0, 0, 3, 50));
+        assert_eq!(file.events[0].text, r"Hello\Nworld");
+
+        let (written, warnings) = file.to_subviewer();
+        assert!(warnings.is_empty());
+        assert!(written.starts_with("[INFORMATION]\n[TITLE]Pilot\n"));
+        assert!(written.ends_with(
+            "00:00:01.00,00:00:03.50\nHello[br]world\n\n00:01:00.00,00:01:02.00\nBye\n\n"
+        ));
+        assert_eq!(SubtitlesFile::from_subviewer(&written).unwrap(), file);
+
+        let sbv = "0:00:01.000,0:00:03.500\nHello\nworld\n\n0:01:00.000,0:01:02.000\nBye\n";
+        let mut file = SubtitlesFile::from_sbv(sbv).unwrap();
+        assert_eq!(file.events[0].text, r"Hello\Nworld");
+        assert_eq!(file.to_sbv(), (format!("{}\n", sbv), vec![]));
+
+        file.events[1].text = r"{\i1}Bye".to_owned();
+        assert_eq!(
+            file.to_sbv().1,
+            [ConversionWarning::UnsupportedTag {
+                event: 1,
+                tag: r"\i1".to_owned()
+            }]
+        );
+        file.events[1].text = r"a\N\Nb\N".to_owned();
+        let again = SubtitlesFile::from_sbv(&file.to_sbv().0).unwrap();
+        assert_eq!(again.events[1].text, r"a\Nb");
+        let file = SubtitlesFile::from_sbv("0:00:01.000,0:00:02.000\n{note} \\h\n").unwrap();
+        assert_eq!(file.events[0].text, "\\{note} \\\u{2060}h");
+        assert!(matches!(
+            SubtitlesFile::from_sbv("0:00:01.000 0:00:02.000\nHi\n"),
+            Err(ParseError::UnexpectedLine { line: 1, .. })
+        ));
+    }
+}
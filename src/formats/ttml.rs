@@ -0,0 +1,1155 @@
+use super::{
+    clock_time, decimal, default_style, identifier, script_info, warn_lossy, ConversionWarning,
+};
+use crate::document::resample::play_res;
+use crate::document::resolved::{find_style, line_style};
+use crate::parsers::ParseError;
+use crate::prelude::{
+    Alignment, BorderStyle, Colour, Dialogue, EventType, ResolvedStyle, ScriptInfo, Styles,
+    SubtitlesFile, Tag, TextSegment, Timestamp,
+};
+use std::fmt::Write;
+
+impl SubtitlesFile {
+    /// Reads a TTML document, such as one of the IMSC1 text profile.
+    ///
+    /// Each `<style>` with an `xml:id` becomes a style of that name and each `<p>` an event
+    /// in the first of its styles. Regions give the alignment and margins, though a
+    /// `tts:textAlign` of the `<p>` or its style wins over the region's. `<span>`s and the
+    /// `tts:` attributes of a `<p>` become override tags and `<br/>` line breaks. The
+    /// resolution is the `tts:extent` of `<tt>` when in pixels, 384x288 otherwise.
+    pub fn from_ttml(input: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(input)?;
+        let root = match tokens.iter().find(|token| !matches!(token, Xml::Text(_))) {
+            Some(Xml::Start {
+                name: "tt",
+                attributes,
+                ..
+            }) => attributes,
+            Some(Xml::Start { name, .. } | Xml::End(name)) => {
+                return Err(ParseError::bad_header(input, name))
+            }
+            _ => return Err(ParseError::bad_header(input, input)),
+        };
+        let mut file = Self {
+            script_info: script_info(),
+            v4styles: vec![default_style()],
+            ..Self::default()
+        };
+        let extent = attribute(root, "extent").and_then(|extent| {
+            let (width, height) = extent.split_once(' ')?;
+            Some((px(width)?, px(height)?))
+        });
+        if let Some((width, height)) = extent {
+            file.script_info.play_res_x = width.round() as i32;
+            file.script_info.play_res_y = height.round() as i32;
+        }
+        let units = Units::new(root, &file.script_info);
+
+        let mut regions: Vec<(&str, Region)> = vec![];
+        // The styles setting `tts:textAlign`, themselves or through the styles they refer to.
+        let mut aligned: Vec<&str> = vec![];
+        // What each open element passes on to the ones inside it.
+        let mut scopes = vec![Scope::default()];
+        let mut at = 0;
+        while at < tokens.len() {
+            let token = &tokens[at];
+            at += 1;
+            let (name, attributes, empty) = match token {
+                Xml::Start {
+                    name,
+                    attributes,
+                    empty,
+                } => (*name, attributes, *empty),
+                Xml::End(_) => {
+                    scopes.pop();
+                    continue;
+                }
+                Xml::Text(_) => continue,
+            };
+            let parent = scopes.last().cloned().unwrap_or_default();
+            match name {
+                "style" => {
+                    if let Some(id) = attribute(attributes, "id") {
+                        let referred = attribute(attributes, "style").unwrap_or_default();
+                        if attribute(attributes, "textAlign").is_some()
+                            || referred.split_whitespace().any(|s| aligned.contains(&s))
+                        {
+                            aligned.push(id);
+                        }
+                        let style = imported_style(id, attributes, &file.v4styles, &units);
+                        match file.v4styles.iter_mut().find(|style| style.name == id) {
+                            Some(existing) => *existing = style,
+                            None => file.v4styles.push(style),
+                        }
+                    }
+                }
+                "region" => {
+                    if let Some(id) = attribute(attributes, "id") {
+                        regions.push((id, Region::new(attributes, &units)));
+                    }
+                }
+                "p" => {
+                    let content = paragraph_content(&tokens[at..], empty);
+                    at += content.len() + usize::from(!empty);
+                    let scope = parent.inner(input, attributes, &units)?;
+                    let Some(end) = scope.end else {
+                        return Err(ParseError::invalid_timestamp(input, name, "end"));
+                    };
+                    let text_align = attribute(attributes, "textAlign").is_some()
+                        || scope.style.is_some_and(|style| aligned.contains(&style));
+                    let region = scope
+                        .region
+                        .and_then(|id| regions.iter().find(|(region, _)| *region == id))
+                        .map(|&(_, region)| match text_align {
+                            true => Region {
+                                column: None,
+                                ..region
+                            },
+                            false => region,
+                        });
+                    file.events.push(paragraph(
+                        attributes,
+                        content,
+                        (scope.begin, end),
+                        scope.style,
+                        region.as_ref(),
+                        &file.v4styles,
+                        &units,
+                    ));
+                    continue;
+                }
+                _ => {}
+            }
+            if !empty {
+                scopes.push(parent.inner(input, attributes, &units)?);
+            }
+        }
+        Ok(file)
+    }
+
+    /// Writes the dialogue lines as a TTML document of the IMSC1 text profile, in order of
+    /// start time.
+    ///
+    /// Each style becomes a `<style>` with its font, colour and outline, and each
+    /// alignment and set of margins in use a `<region>`, which alone aligns the lines.
+    /// Bold, italic, underline, strike-out, the primary colour, the font and its size,
+    /// where tags set them, become `<span>`s; what TTML can't express is left out and
+    /// reported.
+    pub fn to_ttml(&self) -> (String, Vec<ConversionWarning>) {
+        let (x, y) = play_res(self.script_info.play_res_x, self.script_info.play_res_y);
+        let (width, height) = (f64::from(x), f64::from(y));
+        let mut warnings = vec![];
+        let mut events: Vec<_> = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.type_ == EventType::Dialogue)
+            .collect();
+        events.sort_by_key(|(_, event)| event.start);
+
+        let mut placements: Vec<Placement> = vec![];
+        let mut body = String::new();
+        for (index, event) in events {
+            warn_lossy(index, event, ttml_supports, &mut warnings);
+            let placement = Placement::of(event, &self.v4styles);
+            let region = match placements.iter().position(|p| *p == placement) {
+                Some(region) => region + 1,
+                None => {
+                    placements.push(placement);
+                    placements.len()
+                }
+            };
+            let _ = writeln!(
+                body,
+                "      <p begin=\"{}\" end=\"{}\" region=\"region{}\" style=\"{}\">{}</p>",
+                clock_time(event.start, '.'),
+                clock_time(event.end, '.'),
+                region,
+                identifier(&event.style),
+                paragraph_text(event, &self.v4styles),
+            );
+        }
+
+        let mut out = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <tt xmlns=\"http://www.w3.org/ns/ttml\" \
+            xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+            xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" \
+            ttp:profile=\"http://www.w3.org/ns/ttml/profile/imsc1/text\" \
+            tts:extent=\"{}px {}px\" xml:lang=\"\">\n  <head>\n    <styling>\n",
+            x, y
+        );
+        for style in &self.v4styles {
+            let _ = writeln!(
+                out,
+                "      <style xml:id=\"{}\"{}/>",
+                identifier(&style.name),
+                style_attributes(style)
+            );
+        }
+        out += "    </styling>\n    <layout>\n";
+        for (i, placement) in placements.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "      <region xml:id=\"region{}\"{}/>",
+                i + 1,
+                placement.attributes(width, height)
+            );
+        }
+        out += "    </layout>\n  </head>\n  <body>\n    <div>\n";
+        out += &body;
+        out += "    </div>\n  </body>\n</tt>\n";
+        (out, warnings)
+    }
+}
+
+fn ttml_supports(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Bold(_)
+            | Tag::Italic(_)
+            | Tag::Underline(_)
+            | Tag::StrikeOut(_)
+            | Tag::Colour(1, _)
+            | Tag::ColourAlpha(1, _)
+            | Tag::FontName(_)
+            | Tag::FontSize(_)
+            | Tag::Alignment(_)
+            | Tag::LegacyAlignment(_)
+            | Tag::Reset(_)
+            | Tag::DrawingMode(_)
+    )
+}
+
+/// Escapes text for XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';') else {
+            break;
+        };
+        let c = match &rest[1..semicolon] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            reference => reference
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| reference.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped + rest
+}
+
+/// The `tts:` attributes of `style`, sizes in pixels of the script's resolution.
+fn style_attributes(style: &Styles) -> String {
+    let mut attributes = format!(
+        " tts:fontFamily=\"{}\" tts:fontSize=\"{}px\" tts:color=\"{}\"",
+        escape(&style.font_name),
+        decimal(f64::from(style.font_size)),
+        style.primary_colour.to_hex()
+    );
+    if style.bold {
+        attributes += " tts:fontWeight=\"bold\"";
+    }
+    if style.italic {
+        attributes += " tts:fontStyle=\"italic\"";
+    }
+    if let Some(decoration) = decoration(style.underline, style.strikeout) {
+        let _ = write!(attributes, " tts:textDecoration=\"{}\"", decoration);
+    }
+    match style.border_style {
+        BorderStyle::Outline | BorderStyle::Other(_) if style.outline > 0.0 => {
+            let _ = write!(
+                attributes,
+                " tts:textOutline=\"{} {}px\"",
+                style.outline_colour.to_hex(),
+                decimal(f64::from(style.outline))
+            );
+        }
+        BorderStyle::Outline | BorderStyle::Other(_) => {}
+        BorderStyle::OpaqueBox => {
+            let _ = write!(
+                attributes,
+                " tts:backgroundColor=\"{}\"",
+                style.outline_colour.to_hex()
+            );
+        }
+        BorderStyle::BackgroundBox => {
+            let _ = write!(
+                attributes,
+                " tts:backgroundColor=\"{}\"",
+                style.back_colour.to_hex()
+            );
+        }
+    }
+    attributes
+}
+
+fn decoration(underline: bool, strikeout: bool) -> Option<&'static str> {
+    match (underline, strikeout) {
+        (true, true) => Some("underline lineThrough"),
+        (true, false) => Some("underline"),
+        (false, true) => Some("lineThrough"),
+        (false, false) => None,
+    }
+}
+
+/// The text of `event`, in `<span>`s where tags change its formatting.
+fn paragraph_text(event: &Dialogue, styles: &[Styles]) -> String {
+    let fallback = Styles::default();
+    let line = ResolvedStyle::new(line_style(styles, event).unwrap_or(&fallback), event);
+    let mut text = String::new();
+    let mut open = String::new();
+    for run in event.styled_runs(styles) {
+        let span = span_attributes(&line, &run.style);
+        if span != open {
+            if !open.is_empty() {
+                text += "</span>";
+            }
+            if !span.is_empty() {
+                let _ = write!(text, "<span{}>", span);
+            }
+            open = span;
+        }
+        match &run.segment {
+            TextSegment::Text(run) => text += &escape(run),
+            TextSegment::HardBreak => text += "<br/>",
+            TextSegment::SoftBreak => text.push(' '),
+            TextSegment::HardSpace => text.push('\u{a0}'),
+            TextSegment::Override(_) | TextSegment::Drawing(_) => {}
+        }
+    }
+    if !open.is_empty() {
+        text += "</span>";
+    }
+    text
+}
+
+/// The `tts:` attributes of a run in `style` within a line in `line`.
+fn span_attributes(line: &ResolvedStyle, style: &ResolvedStyle) -> String {
+    let mut attributes = String::new();
+    if style.font_name != line.font_name {
+        let _ = write!(
+            attributes,
+            " tts:fontFamily=\"{}\"",
+            escape(&style.font_name)
+        );
+    }
+    if style.font_size != line.font_size {
+        let _ = write!(
+            attributes,
+            " tts:fontSize=\"{}px\"",
+            decimal(style.font_size)
+        );
+    }
+    if style.primary_colour != line.primary_colour {
+        let _ = write!(
+            attributes,
+            " tts:color=\"{}\"",
+            style.primary_colour.to_hex()
+        );
+    }
+    if style.bold != line.bold {
+        let weight = if style.bold { "bold" } else { "normal" };
+        let _ = write!(attributes, " tts:fontWeight=\"{}\"", weight);
+    }
+    if style.italic != line.italic {
+        let font_style = if style.italic { "italic" } else { "normal" };
+        let _ = write!(attributes, " tts:fontStyle=\"{}\"", font_style);
+    }
+    if (style.underline, style.strikeout) != (line.underline, line.strikeout) {
+        let decoration = decoration(style.underline, style.strikeout).unwrap_or("none");
+        let _ = write!(attributes, " tts:textDecoration=\"{}\"", decoration);
+    }
+    attributes
+}
+
+/// Where a line goes: its alignment and margins.
+#[derive(Clone, Copy, PartialEq)]
+struct Placement {
+    alignment: Alignment,
+    margin_l: f64,
+    margin_r: f64,
+    margin_v: f64,
+}
+
+impl Placement {
+    fn of(event: &Dialogue, styles: &[Styles]) -> Self {
+        let fallback = Styles::default();
+        let line = ResolvedStyle::new(line_style(styles, event).unwrap_or(&fallback), event);
+        let alignment = event
+            .state_at(styles, event.start)
+            .runs
+            .first()
+            .map_or(line.alignment, |run| run.style.alignment);
+        Self {
+            alignment,
+            margin_l: line.margin_l,
+            margin_r: line.margin_r,
+            margin_v: line.margin_v,
+        }
+    }
+
+    /// The attributes of a region inside the margins, lines going to its top, middle or
+    /// bottom.
+    fn attributes(&self, width: f64, height: f64) -> String {
+        let numpad = self.alignment.numpad() as usize;
+        let (column, row) = ((numpad - 1) % 3, (numpad - 1) / 3);
+        format!(
+            " tts:origin=\"{}px {}px\" tts:extent=\"{}px {}px\" tts:displayAlign=\"{}\" \
+            tts:textAlign=\"{}\"",
+            decimal(self.margin_l),
+            decimal(self.margin_v),
+            decimal((width - self.margin_l - self.margin_r).max(0.0)),
+            decimal((height - 2.0 * self.margin_v).max(0.0)),
+            ["after", "center", "before"][row],
+            ["left", "center", "right"][column]
+        )
+    }
+}
+
+/// A piece of an XML document, names without their namespace prefix.
+enum Xml<'a> {
+    /// A start tag, `empty` for one closing itself such as `<br/>`.
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, &'a str)>,
+        empty: bool,
+    },
+    End(&'a str),
+    /// Text, with its references still escaped.
+    Text(&'a str),
+}
+
+/// Splits `input` into tags and text, leaving out the XML declaration, comments and the
+/// doctype.
+fn tokenize(input: &str) -> Result<Vec<Xml<'_>>, ParseError> {
+    let mut tokens = vec![];
+    let mut rest = input;
+    while let Some(open) = rest.find('<') {
+        if open > 0 {
+            tokens.push(Xml::Text(&rest[..open]));
+        }
+        let markup = &rest[open..];
+        let end = match markup {
+            _ if markup.starts_with("<!--") => "-->",
+            _ if markup.starts_with("<![CDATA[") => "]]>",
+            _ if markup.starts_with("<?") => "?>",
+            _ => ">",
+        };
+        let Some(close) = markup.find(end) else {
+            return Err(ParseError::unexpected_line(input, markup));
+        };
+        rest = &markup[close + end.len()..];
+        if let Some(text) = markup[..close].strip_prefix("<![CDATA[") {
+            tokens.push(Xml::Text(text));
+            continue;
+        }
+        let tag = &markup[1..close];
+        if end != ">" || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Xml::End(local(name.trim())));
+            continue;
+        }
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        tokens.push(Xml::Start {
+            name: local(&tag[..name_end]),
+            attributes: attributes(input, &tag[name_end..])?,
+            empty,
+        });
+    }
+    if !rest.is_empty() {
+        tokens.push(Xml::Text(rest));
+    }
+    Ok(tokens)
+}
+
+/// `name="value"` pairs, quoted with `"` or `'`.
+fn attributes<'a>(input: &str, mut rest: &'a str) -> Result<Vec<(&'a str, &'a str)>, ParseError> {
+    let mut attributes = vec![];
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(attributes);
+        }
+        let invalid = || ParseError::unexpected_line(input, rest);
+        let (name, value) = rest.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''));
+        let quote = quote.ok_or_else(invalid)?;
+        let end = value[1..].find(quote).ok_or_else(invalid)? + 1;
+        attributes.push((local(name.trim()), &value[1..end]));
+        rest = &value[end + 1..];
+    }
+}
+
+/// `name` without its namespace prefix, `id` for `xml:id`.
+fn local(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+fn attribute<'a>(attributes: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(attribute, _)| *attribute == name)
+        .map(|(_, value)| value.trim())
+}
+
+/// A length in pixels, e.g. `32px`.
+fn px(length: &str) -> Option<f64> {
+    length.trim().strip_suffix("px")?.parse().ok()
+}
+
+/// What the attributes of `<tt>` say about times and lengths.
+struct Units {
+    width: f64,
+    height: f64,
+    frame_rate: f64,
+    tick_rate: f64,
+    /// The number of rows of cells, of which `c` lengths are a number.
+    cell_rows: f64,
+}
+
+impl Units {
+    fn new(root: &[(&str, &str)], script_info: &ScriptInfo) -> Self {
+        let number = |name: &str| {
+            attribute(root, name)
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|value| *value > 0.0)
+        };
+        let multiplier = attribute(root, "frameRateMultiplier")
+            .and_then(|value| value.split_once(' '))
+            .and_then(|(n, d)| Some(n.parse::<f64>().ok()? / d.parse::<f64>().ok()?))
+            .filter(|multiplier| *multiplier > 0.0);
+        let cell_rows = attribute(root, "cellResolution")
+            .and_then(|value| value.split_whitespace().nth(1)?.parse::<f64>().ok())
+            .filter(|rows| *rows > 0.0);
+        Self {
+            width: f64::from(script_info.play_res_x),
+            height: f64::from(script_info.play_res_y),
+            frame_rate: number("frameRate").unwrap_or(30.0) * multiplier.unwrap_or(1.0),
+            tick_rate: number("tickRate").unwrap_or(1.0),
+            cell_rows: cell_rows.unwrap_or(15.0),
+        }
+    }
+
+    /// A clock time such as `00:00:01.500` or `00:00:01:12`, the last number being frames,
+    /// or an offset time such as `1.5s`, `1500ms`, `36f` or `10000t`, in milliseconds. Times
+    /// past the largest `Timestamp` are rejected.
+    fn time(&self, value: &str) -> Option<f64> {
+        let number = |n: &str| n.parse::<f64>().ok().filter(|n| *n >= 0.0);
+        let parts: Vec<&str> = value.trim().split(':').collect();
+        let seconds = match parts[..] {
+            [offset] => {
+                let split = offset.find(|c: char| c.is_ascii_alphabetic())?;
+                let (n, unit) = offset.split_at(split);
+                let unit = match unit {
+                    "h" => 3600.0,
+                    "m" => 60.0,
+                    "s" => 1.0,
+                    "ms" => 0.001,
+                    "f" => 1.0 / self.frame_rate,
+                    "t" => 1.0 / self.tick_rate,
+                    _ => return None,
+                };
+                number(n)? * unit
+            }
+            [h, m, s] => number(h)? * 3600.0 + number(m)? * 60.0 + number(s)?,
+            [h, m, s, f] => {
+                number(h)? * 3600.0 + number(m)? * 60.0 + number(s)? + number(f)? / self.frame_rate
+            }
+            _ => return None,
+        };
+        let largest = Timestamp::from_centis(u32::MAX).as_millis() as f64;
+        Some(seconds * 1000.0).filter(|ms| *ms <= largest)
+    }
+
+    /// A length along the height, relative to `font_size` for `%` and `em`.
+    fn length(&self, value: &str, font_size: f64) -> Option<f64> {
+        let split = value.find(|c: char| c.is_ascii_alphabetic() || c == '%')?;
+        let (n, unit) = value.split_at(split);
+        let n = n.parse::<f64>().ok()?;
+        match unit {
+            "px" => Some(n),
+            "%" => Some(n / 100.0 * font_size),
+            "em" => Some(n * font_size),
+            "c" => Some(n * self.height / self.cell_rows),
+            "rh" => Some(n / 100.0 * self.height),
+            "rw" => Some(n / 100.0 * self.width),
+            _ => None,
+        }
+    }
+
+    /// A coordinate, in pixels or a percentage of `size`.
+    fn coordinate(&self, value: &str, size: f64) -> Option<f64> {
+        match value.strip_suffix('%') {
+            Some(percent) => Some(percent.parse::<f64>().ok()? / 100.0 * size),
+            None => self.length(value, 0.0),
+        }
+    }
+}
+
+/// What an element passes on to the ones inside it.
+#[derive(Clone, Default)]
+struct Scope<'a> {
+    /// Times in milliseconds.
+    begin: f64,
+    end: Option<f64>,
+    style: Option<&'a str>,
+    region: Option<&'a str>,
+}
+
+impl<'a> Scope<'a> {
+    /// The scope of an element inside this one, whose times are relative to its begin.
+    fn inner(
+        &self,
+        input: &str,
+        attributes: &[(&str, &'a str)],
+        units: &Units,
+    ) -> Result<Self, ParseError> {
+        let time = |field: &str| match attribute(attributes, field) {
+            Some(value) => units
+                .time(value)
+                .map(Some)
+                .ok_or_else(|| ParseError::invalid_timestamp(input, value, field)),
+            None => Ok(None),
+        };
+        let begin = self.begin + time("begin")?.unwrap_or(0.0);
+        let end = match (time("end")?, time("dur")?) {
+            (Some(end), _) => Some(self.begin + end),
+            (None, Some(dur)) => Some(begin + dur),
+            (None, None) => self.end,
+        };
+        let style = attribute(attributes, "style")
+            .and_then(|styles| styles.split_whitespace().next())
+            .or(self.style);
+        let region = attribute(attributes, "region").or(self.region);
+        Ok(Self {
+            begin,
+            end,
+            style,
+            region,
+        })
+    }
+}
+
+/// A `<region>`, what it doesn't set being left to the style of the line.
+#[derive(Clone, Copy)]
+struct Region {
+    /// Numpad rows and columns, from the bottom and from the left.
+    row: Option<usize>,
+    column: Option<usize>,
+    origin: (f64, f64),
+    extent: (f64, f64),
+}
+
+impl Region {
+    fn new(attributes: &[(&str, &str)], units: &Units) -> Self {
+        let pair = |name: &str, default: (f64, f64)| {
+            attribute(attributes, name)
+                .and_then(|value| {
+                    let (x, y) = value.split_once(char::is_whitespace)?;
+                    Some((
+                        units.coordinate(x, units.width)?,
+                        units.coordinate(y.trim(), units.height)?,
+                    ))
+                })
+                .unwrap_or(default)
+        };
+        Self {
+            row: attribute(attributes, "displayAlign").and_then(|align| match align {
+                "after" => Some(0),
+                "center" => Some(1),
+                "before" => Some(2),
+                _ => None,
+            }),
+            column: attribute(attributes, "textAlign").and_then(column),
+            origin: pair("origin", (0.0, 0.0)),
+            extent: pair("extent", (units.width, units.height)),
+        }
+    }
+}
+
+/// The numpad column of a `tts:textAlign`.
+fn column(text_align: &str) -> Option<usize> {
+    match text_align {
+        "left" | "start" => Some(0),
+        "center" => Some(1),
+        "right" | "end" => Some(2),
+        _ => None,
+    }
+}
+
+/// A `<style>` named `id`, based on the first style it refers to.
+fn imported_style(
+    id: &str,
+    attributes: &[(&str, &str)],
+    styles: &[Styles],
+    units: &Units,
+) -> Styles {
+    let base = attribute(attributes, "style").and_then(|referred| {
+        referred
+            .split_whitespace()
+            .find_map(|s| find_style(styles, s))
+    });
+    let mut style = Styles {
+        name: id.to_owned(),
+        ..base.cloned().unwrap_or_else(default_style)
+    };
+    apply(&mut style, attributes, units);
+    style
+}
+
+/// Applies the `tts:` attributes TTML and ASS have in common to `style`.
+fn apply(style: &mut Styles, attributes: &[(&str, &str)], units: &Units) {
+    for &(name, value) in attributes {
+        let value = unescape(value.trim());
+        match name {
+            "fontFamily" => {
+                let family = value.split(',').next().unwrap_or_default();
+                style.font_name = family.trim().trim_matches(['"', '\'']).to_owned();
+            }
+            "fontSize" => {
+                // The height, when a width comes before it.
+                let size = value.split_whitespace().next_back().unwrap_or_default();
+                if let Some(size) = units.length(size, f64::from(style.font_size)) {
+                    style.font_size = ((size * 100.0).round() / 100.0) as f32;
+                }
+            }
+            "color" => {
+                if let Some(colour) = colour(&value) {
+                    style.primary_colour = colour;
+                }
+            }
+            "fontWeight" => style.bold = value == "bold",
+            "fontStyle" => style.italic = value == "italic" || value == "oblique",
+            "textDecoration" => {
+                for decoration in value.split_whitespace() {
+                    match decoration {
+                        "underline" => style.underline = true,
+                        "noUnderline" => style.underline = false,
+                        "lineThrough" => style.strikeout = true,
+                        "noLineThrough" => style.strikeout = false,
+                        "none" => (style.underline, style.strikeout) = (false, false),
+                        _ => {}
+                    }
+                }
+            }
+            "textOutline" => {
+                let mut parts = value.split_whitespace().peekable();
+                if let Some(colour) = parts.peek().and_then(|part| colour(part)) {
+                    style.outline_colour = colour;
+                    parts.next();
+                }
+                let font_size = f64::from(style.font_size);
+                match parts
+                    .next()
+                    .and_then(|width| units.length(width, font_size))
+                {
+                    Some(width) => style.outline = ((width * 100.0).round() / 100.0) as f32,
+                    None => style.outline = 0.0,
+                }
+                style.border_style = BorderStyle::Outline;
+            }
+            "backgroundColor" => {
+                if let Some(colour) = colour(&value).filter(|colour| colour.a != 0xFF) {
+                    style.outline_colour = colour;
+                    style.border_style = BorderStyle::OpaqueBox;
+                }
+            }
+            "textAlign" => {
+                if let Some(column) = column(&value) {
+                    let row = (style.alignment.numpad() - 1) / 3;
+                    let numpad = row * 3 + column as i32 + 1;
+                    style.alignment = Alignment::from_numpad(numpad).unwrap_or_default();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `#RRGGBB`, `#RRGGBBAA`, `rgb(...)`, `rgba(...)` or a named colour.
+fn colour(value: &str) -> Option<Colour> {
+    if value.starts_with('#') {
+        return Colour::from_hex(value).ok();
+    }
+    let function = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("));
+    if let Some(arguments) = function.and_then(|f| f.strip_suffix(')')) {
+        let mut rgba = [0, 0, 0, 0xFF];
+        for (i, n) in arguments.split(',').enumerate() {
+            *rgba.get_mut(i)? = n.trim().parse().ok()?;
+        }
+        return Some(Colour::from_rgba(rgba));
+    }
+    let rgb = match value {
+        "transparent" => return Some(Colour::from_rgba([0, 0, 0, 0])),
+        "black" => 0x000000,
+        "silver" => 0xC0C0C0,
+        "gray" => 0x808080,
+        "white" => 0xFFFFFF,
+        "maroon" => 0x800000,
+        "red" => 0xFF0000,
+        "purple" => 0x800080,
+        "fuchsia" | "magenta" => 0xFF00FF,
+        "green" => 0x008000,
+        "lime" => 0x00FF00,
+        "olive" => 0x808000,
+        "yellow" => 0xFFFF00,
+        "navy" => 0x000080,
+        "blue" => 0x0000FF,
+        "teal" => 0x008080,
+        "aqua" | "cyan" => 0x00FFFF,
+        _ => return None,
+    };
+    let [_, r, g, b] = u32::to_be_bytes(rgb);
+    Some(Colour::new(r, g, b, 0))
+}
+
+/// The tokens of a `<p>` up to its end tag, without it.
+fn paragraph_content<'t, 'a>(tokens: &'t [Xml<'a>], empty: bool) -> &'t [Xml<'a>] {
+    if empty {
+        return &[];
+    }
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Xml::Start { empty: false, .. } => depth += 1,
+            Xml::End(_) if depth == 0 => return &tokens[..i],
+            Xml::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// An event from a `<p>`, its formatting where it differs from the style's as override
+/// tags and its region as an alignment and margins.
+fn paragraph(
+    attributes: &[(&str, &str)],
+    content: &[Xml],
+    (begin, end): (f64, f64),
+    style: Option<&str>,
+    region: Option<&Region>,
+    styles: &[Styles],
+    units: &Units,
+) -> Dialogue {
+    let line = style.and_then(|name| find_style(styles, name));
+    let line = line.or_else(|| find_style(styles, "Default"));
+    let fallback = Styles::default();
+    let line = line.unwrap_or(&fallback);
+    let mut event = Dialogue {
+        start: Timestamp::from_millis(begin.round() as u64),
+        end: Timestamp::from_millis(end.max(begin).round() as u64),
+        style: line.name.clone(),
+        ..Dialogue::default()
+    };
+
+    let mut paragraph = line.clone();
+    apply(&mut paragraph, attributes, units);
+    let mut text = format_tags(line, &paragraph);
+    let alignment = paragraph.alignment;
+    let mut formats = vec![paragraph];
+    for token in content {
+        let current = formats.last().unwrap_or(line);
+        match token {
+            Xml::Start {
+                name: "span",
+                attributes,
+                empty,
+            } => {
+                let referred = attribute(attributes, "style").and_then(|referred| {
+                    referred
+                        .split_whitespace()
+                        .find_map(|s| find_style(styles, s))
+                });
+                let mut span = current.clone();
+                if let Some(referred) = referred {
+                    copy_format(&mut span, referred);
+                }
+                apply(&mut span, attributes, units);
+                text += &format_tags(current, &span);
+                if !empty {
+                    formats.push(span);
+                }
+            }
+            Xml::End("span") if formats.len() > 1 => {
+                let span = formats.pop().unwrap_or_default();
+                text += &format_tags(&span, formats.last().unwrap_or(line));
+            }
+            Xml::Start { name: "br", .. } => {
+                text.truncate(text.trim_end_matches(' ').len());
+                text += "\\N";
+            }
+            Xml::Text(run) => {
+                for c in unescape(run).chars() {
+                    match c {
+                        '\u{a0}' => text += "\\h",
+                        ' ' | '\t' | '\r' | '\n' => {
+                            if !text.is_empty() && !text.ends_with(' ') && !text.ends_with("\\N") {
+                                text.push(' ');
+                            }
+                        }
+                        c => text.push(c),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    text.truncate(text.trim_end_matches(' ').len());
+
+    let tags = place(&mut event, line, alignment, region, units);
+    event.text = tags + &text;
+    event
+}
+
+/// Copies what a `<span>` can change of `from` to `to`.
+fn copy_format(to: &mut Styles, from: &Styles) {
+    to.font_name = from.font_name.clone();
+    to.font_size = from.font_size;
+    to.primary_colour = from.primary_colour;
+    to.bold = from.bold;
+    to.italic = from.italic;
+    to.underline = from.underline;
+    to.strikeout = from.strikeout;
+}
+
+/// The override tags going from the formatting of `from` to that of `to`.
+fn format_tags(from: &Styles, to: &Styles) -> String {
+    let mut tags = vec![];
+    if to.font_name != from.font_name {
+        tags.push(Tag::FontName(Some(to.font_name.clone())));
+    }
+    if to.font_size != from.font_size {
+        tags.push(Tag::FontSize(Some(f64::from(to.font_size))));
+    }
+    let (old, new) = (from.primary_colour, to.primary_colour);
+    if (new.r, new.g, new.b) != (old.r, old.g, old.b) {
+        tags.push(Tag::Colour(1, Some(Colour { a: 0, ..new })));
+    }
+    if new.a != old.a {
+        tags.push(Tag::ColourAlpha(1, Some(new.a)));
+    }
+    if to.bold != from.bold {
+        tags.push(Tag::Bold(Some(i32::from(to.bold))));
+    }
+    if to.italic != from.italic {
+        tags.push(Tag::Italic(Some(to.italic)));
+    }
+    if to.underline != from.underline {
+        tags.push(Tag::Underline(Some(to.underline)));
+    }
+    if to.strikeout != from.strikeout {
+        tags.push(Tag::StrikeOut(Some(to.strikeout)));
+    }
+    match tags.is_empty() {
+        true => String::new(),
+        false => format!(
+            "{{{}}}",
+            tags.iter().map(Tag::to_string).collect::<String>()
+        ),
+    }
+}
+
+/// Sets the margins of `event` from `region` where they differ from the style's,
+/// returning an `\an` tag if the alignment, `paragraph`'s where the region doesn't set
+/// it, differs from the style's.
+fn place(
+    event: &mut Dialogue,
+    style: &Styles,
+    paragraph: Alignment,
+    region: Option<&Region>,
+    units: &Units,
+) -> String {
+    let an = |alignment: Alignment| match alignment == style.alignment {
+        true => String::new(),
+        false => format!("{{\\an{}}}", alignment.numpad()),
+    };
+    let Some(region) = region else {
+        return an(paragraph);
+    };
+    let numpad = paragraph.numpad() as usize;
+    let row = region.row.unwrap_or((numpad - 1) / 3);
+    let column = region.column.unwrap_or((numpad - 1) % 3);
+    let alignment = Alignment::from_numpad((row * 3 + column + 1) as i32).unwrap_or_default();
+
+    let margin = |m: f64, style: f32| match m.round() {
+        m if m == f64::from(style) => 0.0,
+        m => m.max(0.0),
+    };
+    let ((x, y), (width, height)) = (region.origin, region.extent);
+    event.margin_l = margin(x, style.margin_l);
+    event.margin_r = margin(units.width - x - width, style.margin_r);
+    match row {
+        0 => event.margin_v = margin(units.height - y - height, style.margin_v),
+        2 => event.margin_v = margin(y, style.margin_v),
+        _ => {}
+    }
+    an(alignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{
+        Alignment, Colour, ConversionWarning, Dialogue, ParseError, Styles, SubtitlesFile,
+        Timestamp,
+    };
+
+    #[test]
+    fn test_ttml() {
+        let ttml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!-- exported -->
+<tt xmlns="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling"
+    xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:frameRate="25"
+    tts:extent="1280px 720px">
+  <head>
+    <styling>
+      <style xml:id="base" tts:fontFamily="'Open Sans', sans-serif" tts:fontSize="48px"
+          tts:color="yellow" tts:textOutline="black 2px"/>
+      <style xml:id="Signs" style="base" tts:fontWeight="bold" tts:textAlign="left"/>
+    </styling>
+    <layout>
+      <region xml:id="bottom" tts:origin="10% 10%" tts:extent="80% 80%"
+          tts:displayAlign="after" tts:textAlign="center"/>
+      <region xml:id="top" tts:origin="0px 36px" tts:extent="1280px 600px"
+          tts:displayAlign="before" tts:textAlign="right"/>
+    </layout>
+  </head>
+  <body region="bottom">
+    <div begin="10s">
+      <p begin="00:00:01.000" dur="1500ms" style="base">Tom &amp;
+        <span tts:fontStyle="italic" tts:color="#FF000080">Jerry</span><br/>next</p>
+      <p begin="00:00:05:10" end="00:00:06:00" region="top" style="Signs">Sign</p>
+    </div>
+  </body>
+</tt>
+"##;
+        let file = SubtitlesFile::from_ttml(ttml).unwrap();
+        assert_eq!(file.script_info.play_res_x, 1280);
+        assert_eq!(file.v4styles.len(), 3);
+        let base = &file.v4styles[1];
+        assert_eq!(base.font_name, "Open Sans");
+        assert_eq!(base.font_size, 48.0);
+        assert_eq!(base.primary_colour, Colour::new(0xFF, 0xFF, 0, 0));
+        assert_eq!((base.outline_colour, base.outline), (Colour::BLACK, 2.0));
+        let signs = &file.v4styles[2];
+        assert!(signs.bold);
+        assert_eq!(signs.font_name, "Open Sans");
+
+        let event = &file.events[0];
+        assert_eq!(event.start, Timestamp::new(0, 0, 11, 0));
+        assert_eq!(event.end, Timestamp::new(0, 0, 12, 50));
+        assert_eq!(event.style, "base");
+        assert_eq!(
+            (event.margin_l, event.margin_r, event.margin_v),
+            (128.0, 128.0, 72.0)
+        );
+        assert_eq!(
+            event.text,
+            r"Tom & {\c&H0000FF&\1a&H7F&\i1}Jerry{\c&H00FFFF&\1a&H00&\i0}\Nnext"
+        );
+        let event = &file.events[1];
+        assert_eq!(event.start, Timestamp::new(0, 0, 15, 40));
+        assert_eq!(
+            (event.margin_l, event.margin_r, event.margin_v),
+            (0.0, 0.0, 36.0)
+        );
+        assert_eq!(event.text, r"{\an7}Sign");
+
+        let (ttml, warnings) = file.to_ttml();
+        assert!(warnings.is_empty());
+        assert!(ttml.contains(
+            "<style xml:id=\"base\" tts:fontFamily=\"Open Sans\" tts:fontSize=\"48px\" \
+            tts:color=\"#FFFF00FF\" tts:textOutline=\"#000000FF 2px\"/>"
+        ));
+        assert!(ttml.contains(
+            "<region xml:id=\"region1\" tts:origin=\"128px 72px\" tts:extent=\"1024px 576px\" \
+            tts:displayAlign=\"after\" tts:textAlign=\"center\"/>"
+        ));
+        assert!(ttml.contains(
+            "<p begin=\"00:00:11.000\" end=\"00:00:12.500\" region=\"region1\" style=\"base\">\
+            Tom &amp; <span tts:color=\"#FF000080\" tts:fontStyle=\"italic\">Jerry</span>\
+            <br/>next</p>"
+        ));
+        // Only the regions keep alignments.
+        let again = SubtitlesFile::from_ttml(&ttml).unwrap();
+        let mut styles = file.v4styles.clone();
+        styles[2].alignment = Alignment::BottomCenter;
+        assert_eq!(again.v4styles, styles);
+        assert_eq!(again.events, file.events);
+
+        let mut file = SubtitlesFile {
+            v4styles: vec![Styles::default()],
+            events: vec![Dialogue {
+                text: r"{\an7}Top left".to_owned(),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        };
+        let (ttml, _) = file.to_ttml();
+        assert!(!ttml
+            .lines()
+            .any(|line| line.contains("<style") && line.contains("textAlign")));
+        assert!(ttml.contains("tts:displayAlign=\"before\" tts:textAlign=\"left\"/>"));
+        let again = SubtitlesFile::from_ttml(&ttml).unwrap();
+        assert_eq!(again.events[0].text, r"{\an7}Top left");
+
+        file.events[0].text = r"{\bord4\k10}a".to_owned();
+        let (_, warnings) = file.to_ttml();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1], ConversionWarning::Karaoke { event: 0 });
+        assert!(matches!(
+            SubtitlesFile::from_ttml("<html></html>"),
+            Err(ParseError::BadHeader { .. })
+        ));
+        assert!(matches!(
+            SubtitlesFile::from_ttml(
+                r#"<tt xmlns="http://www.w3.org/ns/ttml"><body><div>
+                <p begin="0s" end="99999999999999999999h">a</p></div></body></tt>"#
+            ),
+            Err(ParseError::InvalidTimestamp { .. })
+        ));
+    }
+}
@@ -0,0 +1,577 @@
+use super::{
+    clock_time, decimal, default_style, escape_text, identifier, script_info, split_html,
+    warn_lossy, write_html, ConversionWarning, Html,
+};
+use crate::document::resample::play_res;
+use crate::document::resolved::{find_style, line_style};
+use crate::parsers::{clock_timestamp, ParseError};
+use crate::prelude::{
+    Alignment, Colour, Dialogue, EventType, ResolvedStyle, Styles, SubtitlesFile, Tag,
+};
+use std::fmt::Write;
+
+impl SubtitlesFile {
+    /// Reads a WebVTT file.
+    ///
+    /// Each `::cue(.class)` rule of the `STYLE` blocks becomes a style named after the
+    /// class, and a `<c.class>` around a cue's text its style. `<v Name>` gives the name,
+    /// `<b>`, `<i>` and `<u>` become override tags and the cue settings an alignment and
+    /// margins, or a `\pos` for cues placed without a `size`. Regions, notes, ruby and
+    /// karaoke timestamps are left out.
+    pub fn from_webvtt(input: &str) -> Result<Self, ParseError> {
+        let text = input.strip_prefix('\u{feff}').unwrap_or(input);
+        let mut lines = text.lines().peekable();
+        let header = lines.next().unwrap_or(text);
+        match header.strip_prefix("WEBVTT") {
+            Some(rest) if rest.is_empty() || rest.starts_with([' ', '\t']) => {}
+            _ => return Err(ParseError::bad_header(input, header)),
+        }
+        while lines.next_if(|line| !line.trim().is_empty()).is_some() {}
+
+        let mut file = Self {
+            script_info: script_info(),
+            v4styles: vec![default_style()],
+            ..Self::default()
+        };
+        let res = (
+            f64::from(file.script_info.play_res_x),
+            f64::from(file.script_info.play_res_y),
+        );
+        while let Some(line) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut block = vec![line];
+            while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+                block.push(line);
+            }
+            let first = block[0].trim();
+            if first == "NOTE" || first.starts_with("NOTE ") || first == "REGION" {
+                continue;
+            }
+            if first == "STYLE" {
+                read_css(&block[1..].join("\n"), &mut file.v4styles, res.1);
+                continue;
+            }
+            let timing = block.iter().position(|line| line.contains("-->"));
+            let Some(timing) = timing.filter(|&i| i < 2) else {
+                return Err(ParseError::unexpected_line(input, block[0]));
+            };
+            file.events.push(cue(
+                input,
+                block[timing],
+                &block[timing + 1..],
+                &file.v4styles,
+                res,
+            )?);
+        }
+        Ok(file)
+    }
+
+    /// Writes the dialogue lines as WebVTT cues, in order of start time, with a `STYLE`
+    /// block holding a `::cue(.class)` rule per style.
+    ///
+    /// Each cue is placed by `line`, `position`, `align` and `size` settings from its
+    /// alignment and margins, or from its `\pos`, and wraps its text in the class of its
+    /// style and a voice span of its name. `\b`, `\i` and `\u` become HTML tags; what
+    /// WebVTT can't express is left out and reported.
+    pub fn to_webvtt(&self) -> (String, Vec<ConversionWarning>) {
+        let (x, y) = play_res(self.script_info.play_res_x, self.script_info.play_res_y);
+        let res = (f64::from(x), f64::from(y));
+        let mut out = String::from("WEBVTT\n\nSTYLE\n");
+        for style in &self.v4styles {
+            write_css(&mut out, style, res.1);
+        }
+        out.push('\n');
+
+        let mut warnings = vec![];
+        let mut events: Vec<_> = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.type_ == EventType::Dialogue)
+            .collect();
+        events.sort_by_key(|(_, event)| event.start);
+        for (index, event) in events {
+            warn_lossy(index, event, vtt_supports, &mut warnings);
+            let _ = writeln!(
+                out,
+                "{} --> {} {}",
+                clock_time(event.start, '.'),
+                clock_time(event.end, '.'),
+                cue_settings(event, &self.v4styles, res),
+            );
+            let _ = write!(out, "<c.{}>", identifier(&event.style));
+            if !event.name.is_empty() {
+                out += "<v ";
+                escape(&mut out, &event.name);
+                out.push('>');
+            }
+            out += &cue_text(event, &self.v4styles);
+            if !event.name.is_empty() {
+                out += "</v>";
+            }
+            out += "</c>\n\n";
+        }
+        (out, warnings)
+    }
+}
+
+fn vtt_supports(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Bold(_)
+            | Tag::Italic(_)
+            | Tag::Underline(_)
+            | Tag::Alignment(_)
+            | Tag::LegacyAlignment(_)
+            | Tag::Position { .. }
+            | Tag::Reset(_)
+            | Tag::DrawingMode(_)
+    )
+}
+
+fn percent(fraction: f64) -> String {
+    format!("{}%", decimal(fraction * 100.0))
+}
+
+/// Font sizes are relative to the height of the video, as they are to `PlayResY`.
+fn write_css(out: &mut String, style: &Styles, height: f64) {
+    let Colour { r, g, b, .. } = style.primary_colour;
+    let _ = writeln!(out, "::cue(.{}) {{", identifier(&style.name));
+    let _ = writeln!(out, "  font-family: \"{}\";", style.font_name);
+    let font_size = decimal(f64::from(style.font_size) / height * 100.0);
+    let _ = writeln!(out, "  font-size: {}vh;", font_size);
+    let _ = writeln!(out, "  color: #{:02X}{:02X}{:02X};", r, g, b);
+    if style.bold {
+        out.push_str("  font-weight: bold;\n");
+    }
+    if style.italic {
+        out.push_str("  font-style: italic;\n");
+    }
+    let decorations: Vec<_> = [
+        style.underline.then_some("underline"),
+        style.strikeout.then_some("line-through"),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !decorations.is_empty() {
+        let _ = writeln!(out, "  text-decoration: {};", decorations.join(" "));
+    }
+    out.push_str("}\n");
+}
+
+/// Adds or updates a style for each `::cue(.class)` rule of `css`.
+fn read_css(mut css: &str, styles: &mut Vec<Styles>, height: f64) {
+    while let Some(at) = css.find("::cue(") {
+        css = &css[at + "::cue(".len()..];
+        let (Some(selector_end), Some(open), Some(close)) =
+            (css.find(')'), css.find('{'), css.find('}'))
+        else {
+            return;
+        };
+        let selector = css[..selector_end].trim();
+        let declarations = css.get(open + 1..close).unwrap_or_default();
+        css = &css[close + 1..];
+        let Some(name) = selector.strip_prefix('.') else {
+            continue;
+        };
+        let style = match styles.iter().position(|style| style.name == name) {
+            Some(i) => &mut styles[i],
+            None => {
+                styles.push(Styles {
+                    name: name.to_owned(),
+                    ..default_style()
+                });
+                styles.last_mut().unwrap()
+            }
+        };
+        for declaration in declarations.split(';') {
+            let Some((property, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match property.trim().to_ascii_lowercase().as_str() {
+                "font-family" => {
+                    let family = value.split(',').next().unwrap_or(value);
+                    style.font_name = family.trim().trim_matches(['"', '\'']).to_owned();
+                }
+                "font-size" => {
+                    let size = match value.strip_suffix("vh") {
+                        Some(vh) => vh.parse::<f64>().ok().map(|vh| vh / 100.0 * height),
+                        // Pixels of a 1080p video.
+                        None => value
+                            .strip_suffix("px")
+                            .and_then(|px| px.parse::<f64>().ok())
+                            .map(|px| px / 1080.0 * height),
+                    };
+                    if let Some(size) = size {
+                        style.font_size = (size * 100.0).round() as f32 / 100.0;
+                    }
+                }
+                "color" => {
+                    if let Ok(colour) = Colour::from_hex(value) {
+                        style.primary_colour = colour;
+                    }
+                }
+                "font-weight" => {
+                    style.bold = value == "bold"
+                        || value == "bolder"
+                        || value.parse::<u32>().is_ok_and(|weight| weight >= 600);
+                }
+                "font-style" => style.italic = value == "italic" || value == "oblique",
+                "text-decoration" | "text-decoration-line" => {
+                    style.underline = value.contains("underline");
+                    style.strikeout = value.contains("line-through");
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The settings placing `event` as its alignment and margins, or its `\pos`, would.
+fn cue_settings(event: &Dialogue, styles: &[Styles], (width, height): (f64, f64)) -> String {
+    let fallback = Styles::default();
+    let base = ResolvedStyle::new(line_style(styles, event).unwrap_or(&fallback), event);
+    let state = event.state_at(styles, event.start);
+    let alignment = state
+        .runs
+        .first()
+        .map_or(base.alignment, |run| run.style.alignment);
+    // Numpad columns and rows, from the left and from the bottom.
+    let numpad = alignment.numpad() as usize;
+    let (column, row) = ((numpad - 1) % 3, (numpad - 1) / 3);
+    let line_align = ["end", "center", "start"][row];
+    let (position_align, align) = [
+        ("line-left", "left"),
+        ("center", "center"),
+        ("line-right", "right"),
+    ][column];
+    match state.position {
+        Some((x, y)) => format!(
+            "line:{},{} position:{},{} align:{}",
+            percent(y / height),
+            line_align,
+            percent(x / width),
+            position_align,
+            align
+        ),
+        None => {
+            let line = [height - base.margin_v, height / 2.0, base.margin_v][row];
+            let (left, right) = (base.margin_l, width - base.margin_r);
+            let position = [left, (left + right) / 2.0, right][column];
+            format!(
+                "line:{},{} position:{},{} align:{} size:{}",
+                percent(line / height),
+                line_align,
+                percent(position / width),
+                position_align,
+                align,
+                percent((right - left) / width)
+            )
+        }
+    }
+}
+
+/// The text of `event` as cue text, with HTML tags where the tags of the line differ from
+/// its style.
+fn cue_text(event: &Dialogue, styles: &[Styles]) -> String {
+    let fallback = Styles::default();
+    let style = line_style(styles, event).unwrap_or(&fallback);
+    let wanted = |run: &ResolvedStyle| {
+        [
+            (run.bold && !style.bold).then(|| "<b>".to_owned()),
+            (run.italic && !style.italic).then(|| "<i>".to_owned()),
+            (run.underline && !style.underline).then(|| "<u>".to_owned()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    };
+    write_html(&event.styled_runs(styles), wanted, escape)
+}
+
+/// Appends `text` to `html`, escaping what cue text can't hold as is.
+fn escape(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '\u{a0}' => html.push_str("&nbsp;"),
+            c => html.push(c),
+        }
+    }
+}
+
+/// A cue from its timing line and its text.
+fn cue(
+    input: &str,
+    timing: &str,
+    text: &[&str],
+    styles: &[Styles],
+    res: (f64, f64),
+) -> Result<Dialogue, ParseError> {
+    let (start, rest) = timing.split_once("-->").unwrap_or((timing, ""));
+    let mut settings = rest.split_whitespace();
+    let end = settings.next().unwrap_or(rest);
+    let mut event = Dialogue {
+        start: clock_timestamp(input, start.trim(), "Start")?,
+        end: clock_timestamp(input, end, "End")?,
+        ..Dialogue::default()
+    };
+    let text = cue_text_to_ass(&text.join("\n"), &mut event, styles);
+    let fallback = Styles::default();
+    let style = find_style(styles, &event.style).unwrap_or(&fallback);
+    let tags = place(&mut event, settings, style, res);
+    event.text = tags + &text;
+    Ok(event)
+}
+
+/// Sets the style, name and margins of `event` from its cue settings, returning the
+/// override tags needed for the rest.
+fn place<'a>(
+    event: &mut Dialogue,
+    settings: impl Iterator<Item = &'a str>,
+    style: &Styles,
+    (width, height): (f64, f64),
+) -> String {
+    let (mut line, mut line_align, mut position, mut align, mut size) = (None, "", None, "", None);
+    // A line number rather than a percentage: from the top when non-negative, from the
+    // bottom when negative.
+    let mut line_number = None;
+    for setting in settings {
+        let Some((key, value)) = setting.split_once(':') else {
+            continue;
+        };
+        let (raw, alignment) = value.split_once(',').unwrap_or((value, ""));
+        let fraction = raw
+            .strip_suffix('%')
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|v| v / 100.0);
+        match key {
+            "line" => match raw.parse::<i32>() {
+                Ok(number) => line_number = Some(number),
+                Err(_) => (line, line_align) = (fraction, alignment),
+            },
+            "position" => position = fraction,
+            "align" => align = value,
+            "size" => size = fraction,
+            _ => {}
+        }
+    }
+    let row = match (line_number, line, line_align) {
+        (Some(number), ..) if number >= 0 => 2,
+        (Some(_), ..) => 0,
+        (None, Some(_), "start") => 2,
+        (None, Some(_), "center") => 1,
+        _ => 0,
+    };
+    let column = match align {
+        "left" | "start" => 0,
+        "right" | "end" => 2,
+        _ => 1,
+    };
+    let alignment = Alignment::from_numpad(row * 3 + column + 1).unwrap_or_default();
+    let mut tags = String::new();
+    if alignment != style.alignment {
+        let _ = write!(tags, "\\an{}", alignment.numpad());
+    }
+
+    match (line, position, size) {
+        (Some(y), Some(x), None) => {
+            let (x, y) = ((x * width * 1000.0).round(), (y * height * 1000.0).round());
+            let _ = write!(tags, "\\pos({},{})", x / 1000.0, y / 1000.0);
+        }
+        _ => {
+            let margin = |m: f64, style: f32| match m.round() {
+                m if m == f64::from(style) => 0.0,
+                m => m,
+            };
+            if let Some(line) = line {
+                let margin_v = [height - line * height, 0.0, line * height][row as usize];
+                if row != 1 {
+                    event.margin_v = margin(margin_v, style.margin_v);
+                }
+            }
+            if let Some(number) = line_number {
+                // Lines of cue text are 5.33% of the video high.
+                let lines = match number {
+                    0.. => number,
+                    _ => -number - 1,
+                };
+                let margin_v = f64::from(style.margin_v) + f64::from(lines) * height * 0.0533;
+                event.margin_v = margin(margin_v, style.margin_v);
+            }
+            if let Some(size) = size {
+                let box_width = size * width;
+                let position = position.unwrap_or([0.0, 0.5, 1.0][column as usize]);
+                let left = position * width - [0.0, box_width / 2.0, box_width][column as usize];
+                event.margin_l = margin(left, style.margin_l);
+                event.margin_r = margin(width - left - box_width, style.margin_r);
+            }
+        }
+    }
+    match tags.is_empty() {
+        true => tags,
+        false => format!("{{{}}}", tags),
+    }
+}
+
+/// Turns cue text into the text of `event`, setting its style from a `<c.class>` of a
+/// known style around it and its name from a `<v>` span.
+fn cue_text_to_ass(html: &str, event: &mut Dialogue, styles: &[Styles]) -> String {
+    let mut text = String::new();
+    // Whether each open `<c>` switched style.
+    let mut classes: Vec<bool> = vec![];
+    for piece in split_html(html) {
+        let tag = match piece {
+            Html::Text(run) => {
+                text += &unescape(&escape_text(run));
+                continue;
+            }
+            Html::Tag(tag) => tag,
+        };
+        let (name, annotation) = tag.split_once([' ', '\t']).unwrap_or((tag, ""));
+        let mut classes_of = name.split('.');
+        let ass_tag = match classes_of.next().unwrap_or_default() {
+            "b" => Some(Tag::Bold(Some(1))),
+            "/b" => Some(Tag::Bold(Some(0))),
+            "i" => Some(Tag::Italic(Some(true))),
+            "/i" => Some(Tag::Italic(Some(false))),
+            "u" => Some(Tag::Underline(Some(true))),
+            "/u" => Some(Tag::Underline(Some(false))),
+            "c" => {
+                let style = classes_of.find(|class| find_style(styles, class).is_some());
+                classes.push(style.is_some() && !text.is_empty());
+                match style {
+                    Some(style) if text.is_empty() => {
+                        event.style = style.to_owned();
+                        None
+                    }
+                    style => style.map(|style| Tag::Reset(Some(style.to_owned()))),
+                }
+            }
+            "/c" => classes
+                .pop()
+                .unwrap_or_default()
+                .then_some(Tag::Reset(None)),
+            "v" => {
+                if event.name.is_empty() {
+                    event.name = unescape(annotation.trim());
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(tag) = ass_tag {
+            let _ = write!(text, "{{{}}}", tag);
+        }
+    }
+    text.replace('\n', "\\N")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\\h")
+        .replace("&lrm;", "\u{200e}")
+        .replace("&rlm;", "\u{200f}")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{
+        ConversionWarning, Dialogue, ParseError, Styles, SubtitlesFile, Timestamp,
+    };
+
+    #[test]
+    fn test_webvtt() {
+        let vtt = "WEBVTT - subtitles\n\nNOTE a comment\n\nSTYLE\n::cue(.Signs) {\n  \
+            font-family: \"Verdana\", sans-serif;\n  font-size: 5vh;\n  color: #FFFF00;\n  \
+            font-weight: bold;\n}\n\nintro\n00:01.000 --> 00:00:02.500 line:10%,start \
+            align:left position:5% size:50%\n<c.Signs><v Alice>Tom &amp; <i>Jerry</i></v></c>\n\
+            next line\n\n00:03.000 --> 00:04.000 line:50%,center position:25%,center\nPlaced\n";
+        let file = SubtitlesFile::from_webvtt(vtt).unwrap();
+        let signs = &file.v4styles[1];
+        assert_eq!(signs.name, "Signs");
+        assert_eq!(signs.font_name, "Verdana");
+        assert_eq!(signs.font_size, 14.4);
+        assert!(signs.bold);
+        assert_eq!(file.events.len(), 2);
+        let event = &file.events[0];
+        assert_eq!(
+            (event.start, event.end),
+            (Timestamp::new(0, 0, 1, 0), Timestamp::new(0, 0, 2, 50))
+        );
+        assert_eq!(
+            (event.style.as_str(), event.name.as_str()),
+            ("Signs", "Alice")
+        );
+        assert_eq!(
+            (event.margin_l, event.margin_r, event.margin_v),
+            (19.0, 173.0, 29.0)
+        );
+        assert_eq!(event.text, r"{\an7}Tom & {\i1}Jerry{\i0}\Nnext line");
+        assert_eq!(file.events[1].text, r"{\an5\pos(96,144)}Placed");
+
+        let (vtt, warnings) = file.to_webvtt();
+        assert!(warnings.is_empty());
+        assert!(vtt.starts_with("WEBVTT\n\nSTYLE\n::cue(.Default) {\n"));
+        assert!(vtt.contains(
+            "00:00:01.000 --> 00:00:02.500 line:10.07%,start position:4.95%,line-left \
+            align:left size:50%\n<c.Signs><v Alice>Tom &amp; <i>Jerry</i>\nnext line</v></c>\n"
+        ));
+        assert!(vtt.contains(
+            "00:00:03.000 --> 00:00:04.000 line:50%,center position:25%,center align:center\n"
+        ));
+        let again = SubtitlesFile::from_webvtt(&vtt).unwrap();
+        assert_eq!(again.events, file.events);
+        assert_eq!(again.v4styles[1], file.v4styles[1]);
+
+        let mut file = file;
+        file.events[0].text = r"a\N\Nb\N".to_owned();
+        let again = SubtitlesFile::from_webvtt(&file.to_webvtt().0).unwrap();
+        assert_eq!(again.events[0].text, r"a\Nb");
+
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000\nC:\\New {note}&nbsp;\n";
+        let file = SubtitlesFile::from_webvtt(vtt).unwrap();
+        assert_eq!(
+            file.events[0].plain_text(&file.script_info.wrap_style),
+            "C:\\\u{2060}New {note}\u{a0}"
+        );
+
+        let file = SubtitlesFile {
+            v4styles: vec![Styles::default()],
+            events: vec![Dialogue {
+                text: r"{\move(0,0,10,10)\c&H0000FF&}a".to_owned(),
+                ..Dialogue::default()
+            }],
+            ..SubtitlesFile::default()
+        };
+        let (_, warnings) = file.to_webvtt();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0], ConversionWarning::Positioning { event: 0 });
+
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000 line:0\n<v Tom & Jerry>Top\n\n\
+            00:03.000 --> 00:04.000 line:2 align:start\nLower\n\n\
+            00:05.000 --> 00:06.000 line:-1\nBottom\n";
+        let file = SubtitlesFile::from_webvtt(vtt).unwrap();
+        assert_eq!(file.events[0].text, r"{\an8}Top");
+        assert_eq!(file.events[0].name, "Tom & Jerry");
+        assert_eq!(file.events[1].text, r"{\an7}Lower");
+        assert_eq!(file.events[1].margin_v, 41.0);
+        assert_eq!(
+            (file.events[2].text.as_str(), file.events[2].margin_v),
+            ("Bottom", 0.0)
+        );
+        assert!(file.to_webvtt().0.contains("<v Tom &amp; Jerry>Top</v>"));
+        assert!(matches!(
+            SubtitlesFile::from_webvtt("WEBVTTX\n"),
+            Err(ParseError::BadHeader { line: 1, .. })
+        ));
+    }
+}
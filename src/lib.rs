@@ -5,10 +5,12 @@
 /// use std::fs::read_to_string;
 /// 
 /// let f = read_to_string("my.ass").unwrap();
-/// let f = parse_file(f.as_str()).unwrap();
-///
-/// println!("{:#?}", f);
+/// match parse_file(f.as_str()) {
+///     Ok(f) => println!("{:#?}", f),
+///     Err(e) => eprintln!("my.ass: {}", e),
+/// }
 /// ```
 pub mod prelude;
 mod parsers;
 mod document;
+mod formats;
\ No newline at end of file
@@ -0,0 +1,261 @@
+use nom::Offset;
+use std::fmt::{Display, Formatter, Result};
+
+/// Error returned when an ASS document, or one of its sections, cannot be parsed.
+///
+/// Every variant carries the 1-based `line` and `column` where the problem was found
+/// and the offending `text`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    /// A required section header (e.g. `[V4+ Styles]`) was not found.
+    MissingSection {
+        section: String,
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A section header or its `Format:` line is malformed.
+    BadHeader {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A line that does not belong in the section it appears in.
+    UnexpectedLine {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A `Style:`, `Dialogue:` or `Comment:` line with the wrong number of fields.
+    WrongFieldCount {
+        expected: usize,
+        found: usize,
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A field that should hold a number but does not.
+    InvalidNumber {
+        field: String,
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A field that should hold a colour but does not.
+    InvalidColour {
+        field: String,
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// A `Start` or `End` field that is not a valid timestamp.
+    InvalidTimestamp {
+        field: String,
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    /// Timecodes that go back in time, overlap, or are too few to give a frame rate.
+    InvalidTimecodes {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+}
+
+impl ParseError {
+    /// 1-based line number of the offending text.
+    pub fn line(&self) -> usize {
+        match self {
+            Self::MissingSection { line, .. }
+            | Self::BadHeader { line, .. }
+            | Self::UnexpectedLine { line, .. }
+            | Self::WrongFieldCount { line, .. }
+            | Self::InvalidNumber { line, .. }
+            | Self::InvalidColour { line, .. }
+            | Self::InvalidTimestamp { line, .. }
+            | Self::InvalidTimecodes { line, .. } => *line,
+        }
+    }
+
+    /// 1-based column (in characters) of the offending text.
+    pub fn column(&self) -> usize {
+        match self {
+            Self::MissingSection { column, .. }
+            | Self::BadHeader { column, .. }
+            | Self::UnexpectedLine { column, .. }
+            | Self::WrongFieldCount { column, .. }
+            | Self::InvalidNumber { column, .. }
+            | Self::InvalidColour { column, .. }
+            | Self::InvalidTimestamp { column, .. }
+            | Self::InvalidTimecodes { column, .. } => *column,
+        }
+    }
+
+    /// The offending text.
+    pub fn text(&self) -> &str {
+        match self {
+            Self::MissingSection { text, .. }
+            | Self::BadHeader { text, .. }
+            | Self::UnexpectedLine { text, .. }
+            | Self::WrongFieldCount { text, .. }
+            | Self::InvalidNumber { text, .. }
+            | Self::InvalidColour { text, .. }
+            | Self::InvalidTimestamp { text, .. }
+            | Self::InvalidTimecodes { text, .. } => text,
+        }
+    }
+
+    /// Moves the error `lines` lines down, used when a section was parsed on its own
+    /// but the error should point into the whole file.
+    pub(crate) fn shifted(mut self, lines: usize) -> Self {
+        match &mut self {
+            Self::MissingSection { line, .. }
+            | Self::BadHeader { line, .. }
+            | Self::UnexpectedLine { line, .. }
+            | Self::WrongFieldCount { line, .. }
+            | Self::InvalidNumber { line, .. }
+            | Self::InvalidColour { line, .. }
+            | Self::InvalidTimestamp { line, .. }
+            | Self::InvalidTimecodes { line, .. } => *line += lines,
+        }
+        self
+    }
+
+    pub(crate) fn missing_section(input: &str, at: &str, section: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::MissingSection {
+            section: section.to_string(),
+            line,
+            column,
+            text: first_line(at).to_string(),
+        }
+    }
+
+    pub(crate) fn bad_header(input: &str, at: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::BadHeader {
+            line,
+            column,
+            text: first_line(at).to_string(),
+        }
+    }
+
+    pub(crate) fn unexpected_line(input: &str, at: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::UnexpectedLine {
+            line,
+            column,
+            text: first_line(at).to_string(),
+        }
+    }
+
+    pub(crate) fn wrong_field_count(input: &str, at: &str, expected: usize, found: usize) -> Self {
+        let (line, column) = position(input, at);
+        Self::WrongFieldCount {
+            expected,
+            found,
+            line,
+            column,
+            text: first_line(at).to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_number(input: &str, at: &str, field: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::InvalidNumber {
+            field: field.to_string(),
+            line,
+            column,
+            text: at.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_colour(input: &str, at: &str, field: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::InvalidColour {
+            field: field.to_string(),
+            line,
+            column,
+            text: at.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_timestamp(input: &str, at: &str, field: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::InvalidTimestamp {
+            field: field.to_string(),
+            line,
+            column,
+            text: at.to_string(),
+        }
+    }
+
+    pub(crate) fn invalid_timecodes(input: &str, at: &str) -> Self {
+        let (line, column) = position(input, at);
+        Self::InvalidTimecodes {
+            line,
+            column,
+            text: first_line(at).to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "line {}, column {}: ", self.line(), self.column())?;
+        match self {
+            Self::MissingSection { section, text, .. } => {
+                write!(f, "expected section [{}], found {:?}", section, text)
+            }
+            Self::BadHeader { text, .. } => write!(f, "malformed section header {:?}", text),
+            Self::UnexpectedLine { text, .. } => write!(f, "unexpected line {:?}", text),
+            Self::WrongFieldCount {
+                expected,
+                found,
+                text,
+                ..
+            } => write!(
+                f,
+                "expected {} fields but found {} in {:?}",
+                expected, found, text
+            ),
+            Self::InvalidNumber { field, text, .. } => {
+                write!(f, "invalid number {:?} in field `{}`", text, field)
+            }
+            Self::InvalidColour { field, text, .. } => {
+                write!(f, "invalid colour {:?} in field `{}`", text, field)
+            }
+            Self::InvalidTimestamp { field, text, .. } => {
+                write!(f, "invalid timestamp {:?} in field `{}`", text, field)
+            }
+            Self::InvalidTimecodes { text, .. } => write!(f, "invalid timecodes at {:?}", text),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns the 1-based line and column at which `fragment` starts inside `input`.
+/// `fragment` must be a sub-slice of `input`.
+pub(crate) fn position(input: &str, fragment: &str) -> (usize, usize) {
+    let consumed = &input[..input.offset(fragment)];
+    let mut line = 1;
+    let mut column = 1;
+    let mut chars = consumed.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => {
+                line += 1;
+                column = 1;
+            }
+            _ => column += 1,
+        }
+    }
+    (line, column)
+}
+
+fn first_line(input: &str) -> &str {
+    input.split(['\r', '\n']).next().unwrap_or_default()
+}
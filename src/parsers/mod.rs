@@ -1,93 +1,322 @@
+mod error;
+mod parse_colour;
+mod parse_drawing;
 mod parse_events;
+mod parse_keyframes;
 mod parse_project_garbage;
 mod parse_script_info;
+mod parse_tags;
+mod parse_timecodes;
+mod parse_timestamp;
 mod parse_v4_styles;
-use std::io::Error;
+pub(crate) use error::position;
+pub(crate) use parse_colour::colour;
+pub(crate) use parse_drawing::drawing;
+pub(crate) use parse_keyframes::parse_keyframes;
+pub(crate) use parse_tags::parse_text;
+pub(crate) use parse_timecodes::parse_timecodes;
+pub(crate) use parse_timestamp::{clock_timestamp, lenient_timestamp, strict_timestamp};
+pub use error::ParseError;
 
+use crate::document::document::{EVENT_FORMAT, SSA_STYLE_FORMAT, STYLE_FORMAT};
 use crate::prelude::{
-    Dialogue, ProjectGarbage, ScriptInfo, Styles, SubtitlesFile, WrapStyle, YcbcrMatrix,
+    Dialogue, ExtraSection, ProjectGarbage, ScriptInfo, Styles, SubtitlesFile, WrapStyle, YcbcrMatrix,
 };
 
 use nom::{
-    bytes::complete::{tag, take_until1},
-    character::complete::{digit0, line_ending},
-    combinator::{map_res, opt},
-    multi::many0,
+    branch::alt,
+    bytes::complete::{tag, take_till, take_till1},
+    character::complete::{char, digit0, multispace0, space0},
+    combinator::{eof, map, map_res, verify},
     number::complete::float,
-    sequence::{terminated, tuple},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
+use std::str::FromStr;
 
 // https://github.com/zkat/miette/discussions/282
 
-pub fn parse_script_info_section(input: &str) -> IResult<&str, ScriptInfo> {
-    let (input, _) = tuple((tag("\u{feff}[Script Info]"), line_ending))(input)?;
-    let (input, si) = terminated(parse_script_info::script_info, many0(line_ending))(input)?;
-    Ok((input, si))
+pub fn parse_script_info_section(input: &str) -> Result<(&str, ScriptInfo), ParseError> {
+    let rest = expect_section(input, "Script Info")?;
+    let (after, si) = parse_script_info::script_info(rest)
+        .map_err(|_| ParseError::unexpected_line(input, rest))?;
+    // The numeric keys whose values don't parse are kept as unknown ones otherwise.
+    for line in rest[..rest.len() - after.len()].split(['\r', '\n']) {
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim_end();
+            if matches!(key, "PlayResX" | "PlayResY" | "WrapStyle") {
+                number::<i32>(input, value.trim(), key)?;
+            }
+        }
+    }
+    Ok((end_of_section(input, after)?, si))
+}
+
+pub fn parse_apg_section(input: &str) -> Result<(&str, ProjectGarbage), ParseError> {
+    let rest = expect_section(input, "Aegisub Project Garbage")?;
+    let (rest, apg) = parse_project_garbage::parse_apg(rest)
+        .map_err(|_| ParseError::unexpected_line(input, rest))?;
+    Ok((end_of_section(input, rest)?, apg))
 }
 
-pub fn parse_apg_section(input: &str) -> IResult<&str, ProjectGarbage> {
-    let (input, _) = tuple((tag("[Aegisub Project Garbage]"), line_ending))(input)?;
-    let (input, apg) = terminated(parse_project_garbage::parse_apg, many0(line_ending))(input)?;
-    Ok((input, apg))
+pub fn parse_styles_section(input: &str) -> Result<(&str, Vec<Styles>), ParseError> {
+    let rest = expect_section(input, "V4+ Styles")?;
+    let (rest, format) = parse_format(input, rest, &STYLE_FORMAT)?;
+    let (rest, vfs) = parse_v4_styles::parse_v4_styles(input, &format, rest, false)?;
+    Ok((end_of_section(input, rest)?, vfs))
 }
 
-pub fn parse_styles_section(input: &str) -> IResult<&str, Vec<Styles>> {
-    let (input, _) = tuple((tag("[V4+ Styles]"),line_ending,tag(
-        "Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding",
-    ),line_ending))(input)?;
-    let (input, vfs) = terminated(parse_v4_styles::parse_v4_styles, many0(line_ending))(input)?;
-    Ok((input, vfs))
+/// Parses the `[V4 Styles]` of an SSA v4 file, converting its alignments to numpad ones.
+pub fn parse_ssa_styles_section(input: &str) -> Result<(&str, Vec<Styles>), ParseError> {
+    let rest = expect_section(input, "V4 Styles")?;
+    let (rest, format) = parse_format(input, rest, &SSA_STYLE_FORMAT)?;
+    let (rest, vfs) = parse_v4_styles::parse_v4_styles(input, &format, rest, true)?;
+    Ok((end_of_section(input, rest)?, vfs))
 }
 
-pub fn parse_events_section(input: &str) -> IResult<&str, Vec<Dialogue>> {
-    let (input, _) = tuple((
-        tag("[Events]"),
-        line_ending,
-        tag("Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text"),
-        line_ending,
-    ))(input)?;
-    let (input, evt) = terminated(parse_events::parse_events, many0(line_ending))(input)?;
-    Ok((input, evt))
+pub fn parse_events_section(input: &str) -> Result<(&str, Vec<Dialogue>), ParseError> {
+    let rest = expect_section(input, "Events")?;
+    let (rest, format) = parse_format(input, rest, &EVENT_FORMAT)?;
+    let (rest, evt) = parse_events::parse_events(input, &format, rest)?;
+    Ok((end_of_section(input, rest)?, evt))
 }
 
 /// Parses an ASS ***file***.
-pub fn parse_file(input: &str) -> Result<SubtitlesFile, Error> {
-    let (input, si) = parse_script_info_section(input).expect("parse_script_info_section() failed");
+///
+/// The byte order mark is optional, lines may end in `\r\n`, `\n` or `\r`,
+/// and sections may come in any order. `[Script Info]` is the only required section.
+///
+/// SSA v4 files, those with `[V4 Styles]`, are read into the same model: their
+/// `ScriptType` becomes `v4.00+` and the `Marked` column of their events is dropped, so
+/// that [`SubtitlesFile::write_to`] writes them as ASS. See [`SubtitlesFile::to_ssa`]
+/// to write SSA v4 instead.
+pub fn parse_file(input: &str) -> Result<SubtitlesFile, ParseError> {
+    // Section parsers report positions relative to their own input.
+    let shift = |rest: &str| {
+        let lines = position(input, rest).0 - 1;
+        move |e: ParseError| e.shifted(lines)
+    };
+
+    let mut file = SubtitlesFile::default();
+    let mut has_script_info = false;
+    let mut is_ssa = false;
+    let mut rest = skip_whitespace(input.strip_prefix('\u{feff}').unwrap_or(input));
+    while !rest.is_empty() {
+        let (body, name) =
+            section_header(rest).map_err(|_| ParseError::unexpected_line(input, rest))?;
+        rest = match name.to_ascii_lowercase().as_str() {
+            "script info" => {
+                let (r, si) = parse_script_info_section(rest).map_err(shift(rest))?;
+                file.script_info = si;
+                has_script_info = true;
+                r
+            }
+            "aegisub project garbage" => {
+                let (r, apg) = parse_apg_section(rest).map_err(shift(rest))?;
+                file.project_garbage = Some(apg);
+                r
+            }
+            "v4+ styles" => {
+                let (r, vfs) = parse_styles_section(rest).map_err(shift(rest))?;
+                file.v4styles.extend(vfs);
+                r
+            }
+            "v4 styles" => {
+                let (r, vfs) = parse_ssa_styles_section(rest).map_err(shift(rest))?;
+                file.v4styles.extend(vfs);
+                is_ssa = true;
+                r
+            }
+            "events" => {
+                let (r, evt) = parse_events_section(rest).map_err(shift(rest))?;
+                file.events.extend(evt);
+                r
+            }
+            _ => {
+                let (r, lines) = section_body(body);
+                file.extra_sections.push(extra_section(name, lines));
+                r
+            }
+        };
+        rest = skip_whitespace(rest);
+    }
+
+    if !has_script_info {
+        return Err(ParseError::missing_section(input, input, "Script Info"));
+    }
+    if is_ssa {
+        file.script_info.script_type = String::from("v4.00+");
+        for event in &mut file.events {
+            event
+                .extra_fields
+                .retain(|(column, _)| !column.eq_ignore_ascii_case("marked"));
+        }
+    }
+    Ok(file)
+}
+
+/// Matches a line ending: `\r\n`, `\n` or a lone `\r`.
+pub(crate) fn eol(input: &str) -> IResult<&str, &str> {
+    alt((tag("\r\n"), tag("\n"), tag("\r")))(input)
+}
+
+/// Matches `name` followed by a colon, e.g. the `Title:` of `Title: My script`.
+pub(crate) fn key<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(tag(name), tuple((space0, char(':'), space0)))
+}
+
+/// Parses a `[Section Name]` line and returns the section name.
+pub(crate) fn section_header(input: &str) -> IResult<&str, &str> {
+    terminated(
+        delimited(
+            char('['),
+            take_till1(|c| matches!(c, ']' | '\r' | '\n')),
+            char(']'),
+        ),
+        pair(space0, alt((eol, eof))),
+    )(input)
+}
 
-    // TODO: None if not found...
-    let (input, apg) = opt(parse_apg_section)(input).expect("parse_apg_section() failed");
+/// Skips an optional byte order mark and blank lines, then expects the header of `section`.
+fn expect_section<'a>(input: &'a str, section: &str) -> Result<&'a str, ParseError> {
+    let at = skip_whitespace(input.strip_prefix('\u{feff}').unwrap_or(input));
+    match section_header(at) {
+        Ok((rest, name)) if name.eq_ignore_ascii_case(section) => Ok(rest),
+        _ => Err(ParseError::missing_section(input, at, section)),
+    }
+}
+
+/// Checks that only blank lines or the next section header follow a section.
+fn end_of_section<'a>(origin: &str, input: &'a str) -> Result<&'a str, ParseError> {
+    let rest = skip_whitespace(input);
+    if rest.is_empty() || section_header(rest).is_ok() {
+        Ok(rest)
+    } else {
+        Err(ParseError::unexpected_line(origin, rest))
+    }
+}
+
+/// Parses any `Key: value` line, used to keep keys this crate doesn't know.
+pub(crate) fn unknown_field(input: &str) -> IResult<&str, (&str, &str)> {
+    separated_pair(
+        map(
+            verify(take_till1(|c| matches!(c, ':' | '\r' | '\n')), |k: &str| {
+                !k.starts_with('[')
+            }),
+            str::trim_end,
+        ),
+        char(':'),
+        preceded(space0, parse_string0),
+    )(input)
+}
+
+/// Keeps the body of a section this crate doesn't interpret.
+fn extra_section(name: &str, body: &str) -> ExtraSection {
+    let mut lines: Vec<String> = body
+        .split("\r\n")
+        .flat_map(|line| line.split(['\r', '\n']))
+        .map(String::from)
+        .collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    ExtraSection {
+        name: name.to_string(),
+        lines,
+    }
+}
 
-    let (input, vfs) = parse_styles_section(input).expect("parse_styles_section() failed");
+/// Splits `input` at the next section header, returning `(rest, body)`.
+pub(crate) fn section_body(input: &str) -> (&str, &str) {
+    let mut offset = 0;
+    for line in input.split_inclusive(['\r', '\n']) {
+        if section_header(&input[offset..]).is_ok() {
+            break;
+        }
+        offset += line.len();
+    }
+    (&input[offset..], &input[..offset])
+}
 
-    let (_, evt) = parse_events_section(input).expect("parse_events_section() failed");
+/// Parses the `Format:` line of a styles or events section into its column names.
+/// Falls back to `default` if the section has no `Format:` line.
+fn parse_format<'a>(
+    origin: &str,
+    input: &'a str,
+    default: &[&'a str],
+) -> Result<(&'a str, Vec<&'a str>), ParseError> {
+    let input = skip_whitespace(input);
+    let line: IResult<&str, &str> =
+        terminated(preceded(key("Format"), parse_string0), alt((eol, eof)))(input);
+    match line {
+        Ok((rest, columns)) => {
+            let columns: Vec<&str> = columns.split(',').map(str::trim).collect();
+            if columns.iter().any(|c| c.is_empty()) {
+                return Err(ParseError::bad_header(origin, input));
+            }
+            Ok((rest, columns))
+        }
+        Err(_) if input.starts_with("Format") => Err(ParseError::bad_header(origin, input)),
+        Err(_) => Ok((input, default.to_vec())),
+    }
+}
 
-    Ok(SubtitlesFile {
-            script_info: si,
-            project_garbage: apg,
-            v4styles: vfs,
-            events: evt,
-        },
-    )
+/// Skips blank lines and any whitespace.
+fn skip_whitespace(input: &str) -> &str {
+    let blank: IResult<&str, &str> = multispace0(input);
+    blank.map_or(input, |(rest, _)| rest)
 }
 
+/// Parses the rest of the line, which must not be empty.
 pub(crate) fn parse_string1(input: &str) -> IResult<&str, &str> {
-    take_until1("\r\n")(input)
+    take_till1(|c| c == '\r' || c == '\n')(input)
+}
+
+/// Parses the rest of the line, which may be empty.
+pub(crate) fn parse_string0(input: &str) -> IResult<&str, &str> {
+    take_till(|c| c == '\r' || c == '\n')(input)
 }
 
 /// Parses an integer.
 pub(crate) fn integer(input: &str) -> IResult<&str, i32> {
     map_res(digit0, |s: &str| s.parse::<i32>())(input)
 }
+/// Splits a `Style:` or event line into exactly `count` comma separated fields.
+/// The last field keeps any remaining commas.
+pub(crate) fn split_fields<'a>(
+    input: &str,
+    line: &'a str,
+    count: usize,
+) -> Result<Vec<&'a str>, ParseError> {
+    let fields: Vec<&str> = line.splitn(count, ',').collect();
+    if fields.len() != count {
+        return Err(ParseError::wrong_field_count(
+            input,
+            line,
+            count,
+            fields.len(),
+        ));
+    }
+    Ok(fields)
+}
+
+/// Parses a numeric field, reporting `field` as the culprit on failure.
+pub(crate) fn number<T: FromStr>(input: &str, value: &str, field: &str) -> Result<T, ParseError> {
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| ParseError::invalid_number(input, value, field))
+}
+
 /// Parses a float.
 pub(crate) fn floating(input: &str) -> IResult<&str, f32> {
     map_res(float, |s| s.to_string().parse::<f32>())(input)
 }
 
 #[derive(Debug, PartialEq)]
-pub enum EventTypeField {
-    Dialogue(String),
-    Comment(String),
+pub enum EventTypeField<'a> {
+    Dialogue(&'a str),
+    Comment(&'a str),
 }
 
 #[derive(Debug, PartialEq)]
@@ -106,6 +335,32 @@ pub enum ScriptInfoField {
     ScriptUpdatedBy(String),
     UpdateDetails(String),
     ScriptType(String),
+    Comment(String),
+    Unknown(String, String),
+}
+
+impl ScriptInfoField {
+    /// The key of the field, `None` for a comment.
+    fn key(&self) -> Option<&str> {
+        Some(match self {
+            Self::Title(_) => "Title",
+            Self::WrapStyle(_) => "WrapStyle",
+            Self::ScaledBorderAndShadow(_) => "ScaledBorderAndShadow",
+            Self::YcbcrMatrix(_) => "YCbCr Matrix",
+            Self::OriginalScript(_) => "Original Script",
+            Self::PlayResX(_) => "PlayResX",
+            Self::PlayResY(_) => "PlayResY",
+            Self::OriginalTranslation(_) => "Original Translation",
+            Self::OriginalEditing(_) => "Original Editing",
+            Self::OriginalTiming(_) => "Original Timing",
+            Self::SynchPoint(_) => "Synch Point",
+            Self::ScriptUpdatedBy(_) => "Script Updated By",
+            Self::UpdateDetails(_) => "Update Details",
+            Self::ScriptType(_) => "ScriptType",
+            Self::Comment(_) => return None,
+            Self::Unknown(key, _) => key,
+        })
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -119,12 +374,20 @@ pub enum ProjectGarbageField {
     ScrollPosition(i32),
     ActiveLine(i32),
     VideoPosition(i32),
+    Unknown(String, String),
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_script_info_section;
-    use crate::prelude::{ScriptInfo, WrapStyle};
+    use super::{
+        parse_events_section, parse_file, parse_script_info_section, parse_styles_section,
+        ParseError,
+    };
+    use crate::prelude::{
+        Alignment, BorderStyle, Dialogue, ExtraSection, ScriptInfo, Styles, Timestamp, WrapStyle,
+        YcbcrMatrix,
+    };
+
     #[test]
     fn test_script_info_parser() {
         let pretend_this_is_a_file = "\u{feff}[Script Info]\r\nTitle: Translation File Test Doc\r\nScriptType: v4.00+\r\nWrapStyle: 0\r\nScaledBorderAndShadow: yes\r\nYCbCr Matrix: None\r\nOriginal Script: OGS\r\nPlayResX: 1920\r\nPlayResY: 1080\r\nOriginal Translation: TL By John Doe\r\nOriginal Editing: ED By John Doe\r\nOriginal Timing: TIMING By John Doe\r\nSynch Point: SYNCING By John Doe\r\nScript Updated By: UPDATED BY By John Doe\r\nUpdate Details: UPDATED DETAILS By John Doe\r\n";
@@ -144,7 +407,149 @@ mod tests {
             synch_point: "SYNCING By John Doe".to_owned(),
             script_updated_by: "UPDATED BY By John Doe".to_owned(),
             update_details: "UPDATED DETAILS By John Doe".to_owned(),
+            extra_fields: vec![],
+            key_order: [
+                "Title",
+                "ScriptType",
+                "WrapStyle",
+                "ScaledBorderAndShadow",
+                "YCbCr Matrix",
+                "Original Script",
+                "PlayResX",
+                "PlayResY",
+                "Original Translation",
+                "Original Editing",
+                "Original Timing",
+                "Synch Point",
+                "Script Updated By",
+                "Update Details",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+        };
+        let parsed = parse_script_info_section(pretend_this_is_a_file).unwrap();
+        assert_eq!(parsed.1.key_order, expected_output.key_order);
+        assert_eq!(parsed, ("", expected_output));
+    }
+
+    #[test]
+    fn test_parse_file_reports_bad_field() {
+        let file = "\u{feff}[Script Info]\r\nTitle: Broken\r\n\r\n[V4+ Styles]\r\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\r\nStyle: Default,Arial,big,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1\r\n\r\n[Events]\r\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\r\n";
+        assert_eq!(
+            parse_file(file).unwrap_err(),
+            ParseError::InvalidNumber {
+                field: "Fontsize".to_owned(),
+                line: 6,
+                column: 22,
+                text: "big".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            parse_file(&file.replace("Title: Broken", "PlayResX: abc")).unwrap_err(),
+            ParseError::InvalidNumber {
+                field: "PlayResX".to_owned(),
+                line: 2,
+                column: 11,
+                text: "abc".to_owned(),
+            }
+        );
+
+        let file = file.replace("Arial,big,", "Arial,48,") + "Dialogue: 0,0:00:01.00,0:00:02.00,Default\r\n";
+        assert!(matches!(
+            parse_file(&file),
+            Err(ParseError::WrongFieldCount { expected: 10, found: 4, line: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn test_style_field_types() {
+        let section = "[V4+ Styles]\nFormat: Name, Fontsize, Bold, Italic, ScaleX, Spacing, BorderStyle, Shadow, Alignment\nStyle: Sign,52.5,1,-1,95.5,0.25,4,1.5,7\n";
+        let expected = Styles {
+            name: "Sign".to_owned(),
+            font_size: 52.5,
+            bold: true,
+            italic: true,
+            scale_x: 95.5,
+            spacing: 0.25,
+            border_style: BorderStyle::BackgroundBox,
+            shadow: 1.5,
+            alignment: Alignment::TopLeft,
+            ..Styles::default()
+        };
+        assert_eq!(
+            parse_styles_section(section),
+            Ok(("", vec![expected.clone()]))
+        );
+        assert_eq!(
+            expected.print(),
+            "Style: Sign,Arial,52.5,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,-1,-1,0,0,95.5,100,0.25,0,4,2,1.5,7,10,10,10,1"
+        );
+        assert!(parse_styles_section(&section.replace(",7\n", ",0\n")).is_err());
+    }
+
+    #[test]
+    fn test_events_follow_format_line() {
+        let section = "[Events]\r\nFormat: Marked, Start, End, Style, Text\r\nDialogue: Marked=0,0:00:01.00,0:00:02.50,Sign,Hello, world\r\n";
+        let expected = Dialogue {
+            start: Timestamp::new(0, 0, 1, 0),
+            end: Timestamp::new(0, 0, 2, 50),
+            style: "Sign".to_owned(),
+            text: "Hello, world".to_owned(),
+            extra_fields: vec![("Marked".to_owned(), "Marked=0".to_owned())],
+            ..Dialogue::default()
         };
-        assert_eq!(parse_script_info_section(pretend_this_is_a_file), Ok(("", expected_output)));
+        assert_eq!(parse_events_section(section), Ok(("", vec![expected])));
+    }
+
+    #[test]
+    fn test_parse_file_tolerates_layout() {
+        let crlf = std::fs::read_to_string("my.ass").unwrap().replace('\n', "\r\n");
+        let expected = parse_file(&crlf).unwrap();
+        assert_eq!(expected.v4styles.len(), 3);
+        assert_eq!(expected.events.len(), 4);
+
+        let lf = crlf.replace("\r\n", "\n").replace('\u{feff}', "");
+        let cr = lf.replace('\n', "\r");
+        let (head, events) = lf.split_at(lf.find("[Events]").unwrap());
+        let reordered = format!("{}\n\n  \n{}", events, head);
+        for input in [&lf, &cr, &reordered] {
+            let parsed = parse_file(input).unwrap();
+            assert_eq!(parsed.script_info, expected.script_info);
+            assert_eq!(parsed.v4styles, expected.v4styles);
+            assert_eq!(parsed.events, expected.events);
+        }
+    }
+
+    #[test]
+    fn test_parse_file_keeps_unknown_lines() {
+        let file = "[Script Info]\n; Script generated by Aegisub\nTitle: Extras\nLayoutResX: 1920\nKerning: yes\nYCbCr Matrix: TV.2020\n\n[Fonts]\nfontname: a.ttf\n[]`8\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\n[Aegisub Extradata]\nData: 1,_aegi_perspective,e#0\n";
+        let parsed = parse_file(file).unwrap();
+        assert_eq!(parsed.script_info.comments, ["Script generated by Aegisub"]);
+        assert_eq!(
+            parsed.script_info.ycbcr_matrix,
+            Some(YcbcrMatrix::Other("TV.2020".to_owned()))
+        );
+        assert!(parsed.script_info.print().contains("YCbCr Matrix: TV.2020\n"));
+        assert_eq!(
+            parsed.script_info.extra_fields,
+            [
+                ("LayoutResX".to_owned(), "1920".to_owned()),
+                ("Kerning".to_owned(), "yes".to_owned())
+            ]
+        );
+        assert_eq!(
+            parsed.extra_sections,
+            [
+                ExtraSection {
+                    name: "Fonts".to_owned(),
+                    lines: vec!["fontname: a.ttf".to_owned(), "[]`8".to_owned()],
+                },
+                ExtraSection {
+                    name: "Aegisub Extradata".to_owned(),
+                    lines: vec!["Data: 1,_aegi_perspective,e#0".to_owned()],
+                },
+            ]
+        );
     }
 }
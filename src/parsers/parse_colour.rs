@@ -0,0 +1,49 @@
+use super::ParseError;
+use crate::prelude::Colour;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, tag_no_case, take_while1},
+    character::complete::{char, digit1},
+    combinator::{all_consuming, map_res, opt, recognize},
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+
+/// `&HAABBGGRR`, `&HBBGGRR&`, `H00FF00` or `0x80FFFFFF`.
+fn prefixed_hex(input: &str) -> IResult<&str, u32> {
+    delimited(
+        pair(opt(char('&')), alt((tag_no_case("h"), tag_no_case("0x")))),
+        hex,
+        opt(char('&')),
+    )(input)
+}
+
+fn hex(input: &str) -> IResult<&str, u32> {
+    map_res(take_while1(|c: char| c.is_ascii_hexdigit()), |h| {
+        u32::from_str_radix(h, 16)
+    })(input)
+}
+
+/// SSA v4 writes colours as signed decimal integers.
+fn decimal(input: &str) -> IResult<&str, u32> {
+    map_res(recognize(preceded(opt(tag("-")), digit1)), |d: &str| {
+        let d = d.parse::<i64>().map_err(|_| ())?;
+        if d < 0 {
+            i32::try_from(d).map(|d| d as u32).map_err(|_| ())
+        } else {
+            u32::try_from(d).map_err(|_| ())
+        }
+    })(input)
+}
+
+/// Parses the colour forms seen in the wild, see [`Colour`].
+pub(crate) fn colour(origin: &str, value: &str, field: &str) -> Result<Colour, ParseError> {
+    let abgr: IResult<&str, u32> = alt((
+        all_consuming(prefixed_hex),
+        all_consuming(decimal),
+        // Hex digits without any `&H`.
+        all_consuming(delimited(opt(char('&')), hex, opt(char('&')))),
+    ))(value.trim());
+    abgr.map(|(_, abgr)| Colour::from_abgr(abgr))
+        .map_err(|_| ParseError::invalid_colour(origin, value, field))
+}
@@ -0,0 +1,66 @@
+use super::parse_tags::float;
+use crate::prelude::{Drawing, DrawingCommand, Point};
+use nom::{
+    branch::alt,
+    character::complete::{anychar, multispace0, one_of},
+    combinator::map,
+    multi::many0,
+    sequence::preceded,
+    IResult,
+};
+
+enum Token {
+    Command(char),
+    Number(f64),
+    Other,
+}
+
+fn token(input: &str) -> IResult<&str, Token> {
+    preceded(
+        multispace0,
+        alt((
+            map(one_of("mnlbspc"), Token::Command),
+            map(float, Token::Number),
+            map(anychar, |_| Token::Other),
+        )),
+    )(input)
+}
+
+/// Reads drawing commands the way libass does: numbers before the first command and
+/// anything that isn't a command or a number are skipped, and every pair of numbers is
+/// a point of the last command.
+pub(crate) fn drawing(input: &str) -> Drawing {
+    let tokens = many0(token)(input).map_or(vec![], |(_, tokens)| tokens);
+    let mut commands = vec![];
+    let mut command = None;
+    let mut numbers = vec![];
+    for token in tokens.into_iter().chain([Token::Command('\0')]) {
+        match token {
+            Token::Number(n) => numbers.push(n),
+            Token::Other => {}
+            Token::Command(next) => {
+                let points: Vec<Point> = numbers
+                    .chunks_exact(2)
+                    .map(|xy| Point::new(xy[0], xy[1]))
+                    .collect();
+                numbers.clear();
+                match command.replace(next) {
+                    Some('m') => commands.extend(points.into_iter().map(DrawingCommand::Move)),
+                    Some('n') => {
+                        commands.extend(points.into_iter().map(DrawingCommand::MoveNoClose))
+                    }
+                    _ if points.is_empty() => {}
+                    Some('l') => commands.push(DrawingCommand::Line(points)),
+                    Some('b') => commands.push(DrawingCommand::Bezier(points)),
+                    Some('s') => commands.push(DrawingCommand::Spline(points)),
+                    Some('p') => commands.push(DrawingCommand::ExtendSpline(points)),
+                    _ => {}
+                }
+                if next == 'c' {
+                    commands.push(DrawingCommand::CloseSpline);
+                }
+            }
+        }
+    }
+    Drawing { commands }
+}
@@ -1,116 +1,84 @@
-use super::EventTypeField;
+use super::{
+    eol, key, lenient_timestamp, number, parse_string0, split_fields, EventTypeField, ParseError,
+};
 use crate::prelude::{Dialogue, EventType};
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{line_ending, multispace0, not_line_ending},
-    combinator::{map, opt},
+    character::complete::multispace0,
+    combinator::{eof, map, opt},
     multi::many0,
     sequence::{delimited, preceded},
     IResult,
 };
 
-/// Parses Dialogues and Comments.
-pub fn parse_events(input: &str) -> IResult<&str, Vec<Dialogue>> {
-    map(
-        many0(preceded(
-            opt(multispace0),
-            alt((parse_dialogue, parse_comment)),
-        )),
-        |d| {
-            let mut evt: Vec<Dialogue> = Vec::new();
-            for field in d {
-                match field {
-                    EventTypeField::Dialogue(line) => {
-                        evt.push(parse_dialogue_line(line, EventType::Dialogue))
-                    }
-                    EventTypeField::Comment(line) => {
-                        evt.push(parse_dialogue_line(line, EventType::Comment))
-                    }
-                }
+/// Parses Dialogues and Comments, mapping their values onto the columns of `format`.
+pub(crate) fn parse_events<'a>(
+    origin: &str,
+    format: &[&str],
+    input: &'a str,
+) -> Result<(&'a str, Vec<Dialogue>), ParseError> {
+    let lines: IResult<&str, Vec<EventTypeField>> = many0(preceded(
+        opt(multispace0),
+        alt((parse_dialogue, parse_comment)),
+    ))(input);
+    let (input, d) = lines.map_err(|_| ParseError::unexpected_line(origin, input))?;
+    let mut evt: Vec<Dialogue> = Vec::new();
+    for field in d {
+        match field {
+            EventTypeField::Dialogue(line) => {
+                evt.push(parse_dialogue_line(origin, format, line, EventType::Dialogue)?)
             }
-            evt
-        },
-    )(input)
+            EventTypeField::Comment(line) => {
+                evt.push(parse_dialogue_line(origin, format, line, EventType::Comment)?)
+            }
+        }
+    }
+    Ok((input, evt))
 }
 
-fn parse_dialogue(input: &str) -> IResult<&str, EventTypeField> {
+fn parse_dialogue(input: &str) -> IResult<&str, EventTypeField<'_>> {
     map(
-        delimited(tag("Dialogue: "), not_line_ending, line_ending),
-        |t: &str| EventTypeField::Dialogue(t.to_string()),
+        delimited(key("Dialogue"), parse_string0, alt((eol, eof))),
+        EventTypeField::Dialogue,
     )(input)
 }
 
-fn parse_comment(input: &str) -> IResult<&str, EventTypeField> {
+fn parse_comment(input: &str) -> IResult<&str, EventTypeField<'_>> {
     map(
-        delimited(tag("Comment: "), not_line_ending, line_ending),
-        |t: &str| EventTypeField::Comment(t.to_string()),
+        delimited(key("Comment"), parse_string0, alt((eol, eof))),
+        EventTypeField::Comment,
     )(input)
 }
 
-fn parse_dialogue_line(d: String, type_: EventType) -> Dialogue {
-    let (layer, start, end, style, name, margin_l, margin_r, margin_v, effect, text) =
-        match d.split_once(',') {
-            Some((layer, rest)) => {
-                let layer = layer;
-                let (start, rest) = rest.split_once(',').unwrap();
-                let (end, rest) = rest.split_once(',').unwrap();
-                let (style, rest) = rest.split_once(',').unwrap();
-                let (name, rest) = rest.split_once(',').unwrap();
-                let (margin_l, rest) = rest.split_once(',').unwrap();
-                let (margin_r, rest) = rest.split_once(',').unwrap();
-                let (margin_v, rest) = rest.split_once(',').unwrap();
-                let (effect, text) = match rest.split_once(',') {
-                    Some((effect, text)) => (Some(effect), text),
-                    None => (None, rest),
-                };
-                (
-                    layer.parse::<i64>().unwrap(),
-                    start,
-                    end,
-                    style.to_owned(),
-                    name.to_owned(),
-                    margin_l.parse::<f64>().unwrap(),
-                    margin_r.parse::<f64>().unwrap(),
-                    margin_v.parse::<f64>().unwrap(),
-                    effect.unwrap_or_default().to_owned(),
-                    text.to_owned(),
-                )
-            }
-            None => {
-                let (layer, rest) = d.split_once(',').unwrap();
-                let (start, rest) = rest.split_once(',').unwrap();
-                let (end, rest) = rest.split_once(',').unwrap();
-                let (style, rest) = rest.split_once(',').unwrap();
-                let (name, text) = match rest.split_once(',') {
-                    Some((name, text)) => (name, text),
-                    None => ("", rest),
-                };
-                (
-                    layer.parse::<i64>().unwrap(),
-                    start,
-                    end,
-                    style.to_owned(),
-                    name.to_owned(),
-                    0.0,
-                    0.0,
-                    0.0,
-                    "".to_owned(),
-                    text.to_owned(),
-                )
-            }
-        };
-    Dialogue {
+/// Columns missing from `format` keep their `Dialogue::default()` value,
+/// unknown columns end up in `Dialogue::extra_fields`.
+fn parse_dialogue_line(
+    origin: &str,
+    format: &[&str],
+    d: &str,
+    type_: EventType,
+) -> Result<Dialogue, ParseError> {
+    let fields = split_fields(origin, d, format.len())?;
+    let mut dialogue = Dialogue {
         type_,
-        layer,
-        start: start.to_string(),
-        end: end.to_string(),
-        style,
-        name,
-        margin_l,
-        margin_r,
-        margin_v,
-        effect,
-        text,
+        ..Dialogue::default()
+    };
+    for (&column, value) in format.iter().zip(fields) {
+        match column.to_ascii_lowercase().as_str() {
+            "layer" => dialogue.layer = number(origin, value, column)?,
+            "start" => dialogue.start = lenient_timestamp(origin, value, column)?,
+            "end" => dialogue.end = lenient_timestamp(origin, value, column)?,
+            "style" => dialogue.style = value.to_owned(),
+            "name" | "actor" => dialogue.name = value.to_owned(),
+            "marginl" => dialogue.margin_l = number(origin, value, column)?,
+            "marginr" => dialogue.margin_r = number(origin, value, column)?,
+            "marginv" => dialogue.margin_v = number(origin, value, column)?,
+            "effect" => dialogue.effect = value.to_owned(),
+            "text" => dialogue.text = value.to_owned(),
+            _ => dialogue
+                .extra_fields
+                .push((column.to_string(), value.to_string())),
+        }
     }
+    Ok(dialogue)
 }
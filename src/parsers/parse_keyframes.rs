@@ -0,0 +1,80 @@
+use super::ParseError;
+use crate::prelude::Keyframes;
+
+/// Parses a keyframe file the way Aegisub detects and reads it, from its first line.
+pub(crate) fn parse_keyframes(input: &str) -> Result<Keyframes, ParseError> {
+    let mut lines = input
+        .strip_prefix('\u{feff}')
+        .unwrap_or(input)
+        .lines()
+        .map(str::trim);
+    let header = lines.next().unwrap_or(input);
+    let xvid_headers = [
+        "# XviD 2pass stat file",
+        "# ffmpeg 2-pass log file, using xvid codec",
+        "# avidemux 2-pass log file, using xvid codec",
+    ];
+    let mut frames = if header.starts_with("# keyframe format v1") {
+        aegisub(input, lines)?
+    } else if xvid_headers.iter().any(|h| header.starts_with(h)) {
+        xvid(lines)
+    } else if header.starts_with("#options:") {
+        x264(input, lines)?
+    } else {
+        return Err(ParseError::bad_header(input, header));
+    };
+    frames.sort_unstable();
+    frames.dedup();
+    Ok(Keyframes { frames })
+}
+
+/// A frame number on each line, after an `fps` line.
+fn aegisub<'a>(
+    input: &'a str,
+    lines: impl Iterator<Item = &'a str>,
+) -> Result<Vec<i64>, ParseError> {
+    lines
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter(|line| !line.to_ascii_lowercase().starts_with("fps"))
+        .map(|line| {
+            line.parse()
+                .map_err(|_| ParseError::invalid_number(input, line, "keyframe"))
+        })
+        .collect()
+}
+
+/// A line per frame starting with its type, `i`, `p` or `b`, which SCXviD writes too. As
+/// in Aegisub, the types are lowercase.
+fn xvid<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<i64> {
+    let types = lines.filter_map(|line| match line.chars().next()? {
+        frame_type @ ('i' | 'p' | 'b') => Some(frame_type),
+        _ => None,
+    });
+    (0..)
+        .zip(types)
+        .filter(|(_, frame_type)| *frame_type == 'i')
+        .map(|(frame, _)| frame)
+        .collect()
+}
+
+/// A line per frame in encoding order, e.g. `in:2 out:1 type:P ...`, where `in` is the
+/// frame number.
+fn x264<'a>(input: &'a str, lines: impl Iterator<Item = &'a str>) -> Result<Vec<i64>, ParseError> {
+    let mut frames = vec![];
+    for line in lines {
+        let field = |name: &str| {
+            line.split_whitespace()
+                .find_map(|field| field.strip_prefix(name))
+        };
+        if !field("type:").is_some_and(|t| t.eq_ignore_ascii_case("i")) {
+            continue;
+        }
+        let frame = field("in:").unwrap_or(line);
+        frames.push(
+            frame
+                .parse()
+                .map_err(|_| ParseError::invalid_number(input, frame, "keyframe"))?,
+        );
+    }
+    Ok(frames)
+}
@@ -1,10 +1,9 @@
 use super::{
-    ProjectGarbageField, {floating, integer, parse_string1},
+    key, unknown_field, ProjectGarbageField, {floating, integer, parse_string0},
 };
 use crate::prelude::ProjectGarbage;
 use nom::{
     branch::alt,
-    bytes::complete::tag,
     character::complete::multispace0,
     combinator::{map, opt},
     multi::many0,
@@ -26,6 +25,9 @@ pub(crate) fn parse_apg(input: &str) -> IResult<&str, ProjectGarbage> {
                 scroll_position,
                 active_line,
                 video_position,
+                map(unknown_field, |(k, v)| {
+                    ProjectGarbageField::Unknown(k.to_string(), v.to_string())
+                }),
             )),
         )),
         |fields| {
@@ -47,6 +49,7 @@ pub(crate) fn parse_apg(input: &str) -> IResult<&str, ProjectGarbage> {
                     ProjectGarbageField::ScrollPosition(sp) => project_garbage.scroll_position = sp,
                     ProjectGarbageField::ActiveLine(al) => project_garbage.active_line = al,
                     ProjectGarbageField::VideoPosition(vp) => project_garbage.video_position = vp,
+                    ProjectGarbageField::Unknown(k, v) => project_garbage.extra_fields.push((k, v)),
                 }
             }
             project_garbage
@@ -56,48 +59,48 @@ pub(crate) fn parse_apg(input: &str) -> IResult<&str, ProjectGarbage> {
 
 fn last_style_storage(input: &str) -> IResult<&str, ProjectGarbageField> {
     map(
-        preceded(tag("Last Style Storage: "), parse_string1),
+        preceded(key("Last Style Storage"), parse_string0),
         |lss: &str| ProjectGarbageField::LastStyleStorage(lss.to_string()),
     )(input)
 }
 fn audio_file(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Audio File: "), parse_string1), |af: &str| {
+    map(preceded(key("Audio File"), parse_string0), |af: &str| {
         ProjectGarbageField::AudioFile(af.to_string())
     })(input)
 }
 fn video_file(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Video File: "), parse_string1), |vf: &str| {
+    map(preceded(key("Video File"), parse_string0), |vf: &str| {
         ProjectGarbageField::VideoFile(vf.to_string())
     })(input)
 }
 fn video_ar_mode(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Video AR Mode: "), floating), |varm| {
+    map(preceded(key("Video AR Mode"), floating), |varm| {
         ProjectGarbageField::VideoArMode(varm)
     })(input)
 }
 fn video_ar_value(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Video AR Value: "), floating), |varv| {
+    map(preceded(key("Video AR Value"), floating), |varv| {
         ProjectGarbageField::VideoArValue(varv)
     })(input)
 }
 fn video_zoom_percent(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Video Zoom Percent: "), floating), |vzp| {
+    map(preceded(key("Video Zoom Percent"), floating), |vzp| {
         ProjectGarbageField::VideoZoomPercent(vzp)
     })(input)
 }
 fn scroll_position(input: &str) -> IResult<&str, ProjectGarbageField> {
     map(
-        preceded(tag("Scroll Position: "), integer),
+        preceded(key("Scroll Position"), integer),
         ProjectGarbageField::ScrollPosition,
     )(input)
 }
 fn active_line(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Active Line: "), integer), |al| {
+    map(preceded(key("Active Line"), integer), |al| {
         ProjectGarbageField::ActiveLine(al)
     })(input)
 }
 fn video_position(input: &str) -> IResult<&str, ProjectGarbageField> {
-    map(preceded(tag("Video Position: "), integer), |vp| {
+    map(preceded(key("Video Position"), integer), |vp| {
         ProjectGarbageField::VideoPosition(vp)
     })(input)
 }
@@ -1,14 +1,13 @@
 use super::{
-    ScriptInfoField, {integer, parse_string1},
+    key, unknown_field, ScriptInfoField, {integer, parse_string0},
 };
 use crate::prelude::{ScriptInfo, WrapStyle, YcbcrMatrix};
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::multispace0,
+    character::complete::{char, multispace0},
     combinator::{map, opt},
     multi::many0,
-    sequence::preceded,
+    sequence::{pair, preceded},
     IResult,
 };
 
@@ -33,11 +32,18 @@ pub(crate) fn script_info(input: &str) -> IResult<&str, ScriptInfo> {
                 script_updated_by,
                 update_details,
                 parse_script_type,
+                comment,
+                map(unknown_field, |(k, v)| {
+                    ScriptInfoField::Unknown(k.to_string(), v.to_string())
+                }),
             )),
         )),
         |fields| {
             let mut script_info = ScriptInfo::default();
             for field in fields {
+                if let Some(key) = field.key() {
+                    script_info.key_order.push(key.to_owned());
+                }
                 match field {
                     ScriptInfoField::Title(title) => script_info.title = title,
                     ScriptInfoField::OriginalScript(ogs) => script_info.original_script = ogs,
@@ -70,6 +76,8 @@ pub(crate) fn script_info(input: &str) -> IResult<&str, ScriptInfo> {
                         script_info.update_details = update_details
                     }
                     ScriptInfoField::ScriptType(st) => script_info.script_type = st,
+                    ScriptInfoField::Comment(c) => script_info.comments.push(c),
+                    ScriptInfoField::Unknown(k, v) => script_info.extra_fields.push((k, v)),
                 }
             }
             script_info
@@ -77,43 +85,51 @@ pub(crate) fn script_info(input: &str) -> IResult<&str, ScriptInfo> {
     )(input)
 }
 
+/// Parses a `;` comment line in the "Script Info" section.
+pub(crate) fn comment(input: &str) -> IResult<&str, ScriptInfoField> {
+    map(
+        preceded(pair(char(';'), opt(char(' '))), parse_string0),
+        |c: &str| ScriptInfoField::Comment(c.to_string()),
+    )(input)
+}
+
 /// Parses "Title" field in the "Script Info" section.
 pub(crate) fn title(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("Title: "), parse_string1), |t: &str| {
+    map(preceded(key("Title"), parse_string0), |t: &str| {
         ScriptInfoField::Title(t.to_string())
     })(input)
 }
 /// Parses "Original Script" field in the "Script Info" section.
 pub(crate) fn original_script(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("Original Script: "), parse_string1),
+        preceded(key("Original Script"), parse_string0),
         |t: &str| ScriptInfoField::OriginalScript(t.to_string()),
     )(input)
 }
 /// Parses "Script Type" field in the "Script Info" section.
 pub(crate) fn parse_script_type(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("ScriptType: "), parse_string1), |st: &str| {
+    map(preceded(key("ScriptType"), parse_string0), |st: &str| {
         ScriptInfoField::ScriptType(st.to_string())
     })(input)
 }
 /// Parses "Original Translation" field in the "Script Info" section.
 pub(crate) fn original_translation(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("Original Translation: "), parse_string1),
+        preceded(key("Original Translation"), parse_string0),
         |ot: &str| ScriptInfoField::OriginalTranslation(ot.to_string()),
     )(input)
 }
 
 /// Parses "Original Editing" field in the "Script Info" section.
 pub(crate) fn original_editing(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("Original Editing: "), parse_string1), |oe| {
+    map(preceded(key("Original Editing"), parse_string0), |oe| {
         ScriptInfoField::OriginalEditing(oe.to_string())
     })(input)
 }
 
 /// Parses "Original Timing" field in the "Script Info" section.
 pub(crate) fn original_timing(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("Original Timing: "), parse_string1), |ot| {
+    map(preceded(key("Original Timing"), parse_string0), |ot| {
         ScriptInfoField::OriginalTiming(ot.to_string())
     })(input)
 }
@@ -121,21 +137,21 @@ pub(crate) fn original_timing(input: &str) -> IResult<&str, ScriptInfoField> {
 /// Parses "Synch-Point" field in the "Script Info" section.
 pub(crate) fn synch_point(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("Synch Point: "), parse_string1),
+        preceded(key("Synch Point"), parse_string0),
         |sync_point: &str| ScriptInfoField::SynchPoint(sync_point.to_string()),
     )(input)
 }
 
 /// Parses "Script Updated By" field in the "Script Info" section.
 pub(crate) fn script_updated_by(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("Script Updated By: "), parse_string1), |sub| {
+    map(preceded(key("Script Updated By"), parse_string0), |sub| {
         ScriptInfoField::ScriptUpdatedBy(sub.to_string())
     })(input)
 }
 
 /// Parses "Update Details" field in the "Script Info" section.
 pub(crate) fn update_details(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("Update Details: "), parse_string1), |ud| {
+    map(preceded(key("Update Details"), parse_string0), |ud| {
         ScriptInfoField::UpdateDetails(ud.to_string())
     })(input)
 }
@@ -143,7 +159,7 @@ pub(crate) fn update_details(input: &str) -> IResult<&str, ScriptInfoField> {
 /// Parses "PlayResX" field in the "Script Info" section.
 pub(crate) fn play_res_x(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("PlayResX: "), integer),
+        preceded(key("PlayResX"), integer),
         ScriptInfoField::PlayResX,
     )(input)
 }
@@ -151,14 +167,14 @@ pub(crate) fn play_res_x(input: &str) -> IResult<&str, ScriptInfoField> {
 /// Parses "PlayResY" field in the "Script Info" section.
 pub(crate) fn play_res_y(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("PlayResY: "), integer),
+        preceded(key("PlayResY"), integer),
         ScriptInfoField::PlayResY,
     )(input)
 }
 
 /// Parses "WrapStyle" field in the "Script Info" section and returns `WrapStyle` enum.
 pub(crate) fn wrap_style(input: &str) -> IResult<&str, ScriptInfoField> {
-    map(preceded(tag("WrapStyle: "), integer), |ws| match ws {
+    map(preceded(key("WrapStyle"), integer), |ws| match ws {
         0 => ScriptInfoField::WrapStyle(WrapStyle::WrapStyle0),
         1 => ScriptInfoField::WrapStyle(WrapStyle::WrapStyle1),
         2 => ScriptInfoField::WrapStyle(WrapStyle::WrapStyle2),
@@ -170,7 +186,7 @@ pub(crate) fn wrap_style(input: &str) -> IResult<&str, ScriptInfoField> {
 /// Parses "ScaledBorderAndShadow" field in the "Script Info" section.
 pub(crate) fn scaled_border_and_shadow(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("ScaledBorderAndShadow: "), parse_string1),
+        preceded(key("ScaledBorderAndShadow"), parse_string0),
         |sbas| match sbas {
             "yes" => ScriptInfoField::ScaledBorderAndShadow(true),
             "no" => ScriptInfoField::ScaledBorderAndShadow(false),
@@ -182,7 +198,7 @@ pub(crate) fn scaled_border_and_shadow(input: &str) -> IResult<&str, ScriptInfoF
 /// Parses "YCbCr Matrix" field in the "Script Info" section and returns `YcbcrMatrix` enum.
 pub(crate) fn ycbcr_matrix(input: &str) -> IResult<&str, ScriptInfoField> {
     map(
-        preceded(tag("YCbCr Matrix: "), parse_string1),
+        preceded(key("YCbCr Matrix"), parse_string0),
         |ycbcr| match ycbcr {
             "None" => ScriptInfoField::YcbcrMatrix(None),
             "TV.601" => ScriptInfoField::YcbcrMatrix(Some(YcbcrMatrix::Tv601)),
@@ -193,10 +209,7 @@ pub(crate) fn ycbcr_matrix(input: &str) -> IResult<&str, ScriptInfoField> {
             "PC.FCC" => ScriptInfoField::YcbcrMatrix(Some(YcbcrMatrix::Pcfcc)),
             "TV.240M" => ScriptInfoField::YcbcrMatrix(Some(YcbcrMatrix::Tv240m)),
             "PC.240M" => ScriptInfoField::YcbcrMatrix(Some(YcbcrMatrix::Pc240m)),
-            unknown => {
-                eprintln!("This YCbCr ({}) is unknown or not implemented, Please file an issue.\nReturning `None` as the default.", unknown);
-                ScriptInfoField::YcbcrMatrix(None)
-            }
+            other => ScriptInfoField::YcbcrMatrix(Some(YcbcrMatrix::Other(other.to_owned()))),
         },
     )(input)
 }
@@ -0,0 +1,501 @@
+use crate::prelude::{
+    Alignment, ClipShape, Colour, Drawing, KaraokeEffect, OverrideItem, Tag, TextSegment,
+    WrapStyle,
+};
+use nom::{
+    branch::alt,
+    bytes::complete::{take_while, take_while1},
+    character::complete::{char, digit0, digit1, one_of},
+    combinator::{all_consuming, map_res, opt, recognize},
+    sequence::{delimited, pair},
+    IResult,
+};
+
+/// Names of the tags written without parentheses. A tag's argument follows its name
+/// directly, so longer names come first (`\fscx` before `\fs`, `\be` before `\b`) as
+/// libass matches them.
+const SIMPLE_TAGS: [&str; 44] = [
+    "xbord", "ybord", "xshad", "yshad", "fax", "fay", "blur", "bord", "frx", "fry", "frz", "fr",
+    "fscx", "fscy", "fsp", "fs", "fe", "fn", "be", "b", "i", "u", "shad", "s", "an", "alpha", "a",
+    "q", "pbo", "p", "kf", "ko", "k", "K", "1c", "2c", "3c", "4c", "c", "1a", "2a", "3a", "4a",
+    "r",
+];
+
+/// Splits `Dialogue.text` into segments the way libass reads it: a `{` without a
+/// closing `}` is plain text, as are `\{` and `\}`, text in a block that isn't a tag is a
+/// comment, and tags whose arguments can't be read are kept as [`Tag::Unknown`].
+pub(crate) fn parse_text(text: &str) -> Vec<TextSegment> {
+    let mut segments = vec![];
+    let mut drawing = false;
+    let mut rest = text;
+    while let Some((run, block, after)) = split_block(rest) {
+        text_run(run, drawing, &mut segments);
+        let items = override_block(block);
+        for item in &items {
+            if let OverrideItem::Tag(Tag::DrawingMode(scale)) = item {
+                drawing = *scale > 0;
+            }
+        }
+        segments.push(TextSegment::Override(items));
+        rest = after;
+    }
+    text_run(rest, drawing, &mut segments);
+    segments
+}
+
+/// Finds the next `{...}` block, returning the text before it, its content and the rest.
+fn split_block(input: &str) -> Option<(&str, &str, &str)> {
+    let open = input
+        .match_indices('{')
+        .map(|(at, _)| at)
+        .find(|&at| !input[..at].ends_with('\\'))?;
+    let close = open + input[open..].find('}')?;
+    Some((&input[..open], &input[open + 1..close], &input[close + 1..]))
+}
+
+/// Splits text between blocks at `\N`, `\n` and `\h`, reading `\{` and `\}` as braces and
+/// other backslashes as plain text. In drawing mode the whole run is drawing commands.
+fn text_run(run: &str, drawing: bool, segments: &mut Vec<TextSegment>) {
+    if drawing {
+        if !run.is_empty() {
+            segments.push(TextSegment::Drawing(Drawing::parse(run)));
+        }
+        return;
+    }
+    let mut text = String::new();
+    let mut rest = run;
+    while let Some(at) = rest.find('\\') {
+        text.push_str(&rest[..at]);
+        let after = &rest[at + 1..];
+        let segment = match after.chars().next() {
+            Some('N') => TextSegment::HardBreak,
+            Some('n') => TextSegment::SoftBreak,
+            Some('h') => TextSegment::HardSpace,
+            Some(brace @ ('{' | '}')) => {
+                text.push(brace);
+                rest = &after[1..];
+                continue;
+            }
+            _ => {
+                text.push('\\');
+                rest = after;
+                continue;
+            }
+        };
+        if !text.is_empty() {
+            segments.push(TextSegment::Text(std::mem::take(&mut text)));
+        }
+        segments.push(segment);
+        rest = &after[1..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(TextSegment::Text(text));
+    }
+}
+
+fn override_block(block: &str) -> Vec<OverrideItem> {
+    let mut items = vec![];
+    let mut rest = block;
+    while let Some(at) = rest.find('\\') {
+        comment(&rest[..at], &mut items);
+        rest = tag(&rest[at + 1..], &mut items);
+    }
+    comment(rest, &mut items);
+    items
+}
+
+/// Keeps text renderers skip, unless it is only whitespace.
+fn comment(text: &str, items: &mut Vec<OverrideItem>) {
+    if !text.trim().is_empty() {
+        items.push(OverrideItem::Comment(text.to_string()));
+    }
+}
+
+/// Reads the tag after a backslash, with any comment trailing its argument, and
+/// returns the rest of the block.
+fn tag<'a>(input: &'a str, items: &mut Vec<OverrideItem>) -> &'a str {
+    let name_end = input.find(['(', '\\']).unwrap_or(input.len());
+    let (name, after) = input.split_at(name_end);
+    if let Some(args) = after.strip_prefix('(') {
+        let (args, rest) = match closing_paren(args) {
+            Some(close) => (&args[..close], &args[close + 1..]),
+            // libass reads the arguments up to the end of the block.
+            None => (args, ""),
+        };
+        let raw = &input[..input.len() - rest.len()];
+        let tag = complex_tag(name.trim(), args).unwrap_or_else(|| Tag::Unknown(raw.to_string()));
+        items.push(OverrideItem::Tag(tag));
+        return rest;
+    }
+    match simple_tag(name.trim_start()) {
+        Some((tag, trailing)) => {
+            items.push(OverrideItem::Tag(tag));
+            comment(trailing, items);
+        }
+        None => items.push(OverrideItem::Tag(Tag::Unknown(name.trim().to_string()))),
+    }
+    after
+}
+
+/// Index of the `)` closing an argument list, `\t` and `\clip` arguments may nest.
+fn closing_paren(args: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Reads a tag with parenthesized arguments, `None` if its name or arguments are unknown.
+fn complex_tag(name: &str, args: &str) -> Option<Tag> {
+    match name {
+        "t" => return transform(args),
+        "clip" => return clip(false, args),
+        "iclip" => return clip(true, args),
+        _ => {}
+    }
+    let args = args.split(',').map(number).collect::<Option<Vec<f64>>>()?;
+    let time = |t: f64| t as i32;
+    let alpha = |a: f64| (a as i32).clamp(0, 255) as u8;
+    match (name, args.as_slice()) {
+        ("pos", &[x, y]) => Some(Tag::Position { x, y }),
+        ("org", &[x, y]) => Some(Tag::Origin { x, y }),
+        ("move", &[x1, y1, x2, y2]) => Some(Tag::Move {
+            x1,
+            y1,
+            x2,
+            y2,
+            times: None,
+        }),
+        ("move", &[x1, y1, x2, y2, t1, t2]) => Some(Tag::Move {
+            x1,
+            y1,
+            x2,
+            y2,
+            times: Some((time(t1), time(t2))),
+        }),
+        ("fad" | "fade", &[fade_in, fade_out]) => Some(Tag::Fade {
+            fade_in: time(fade_in),
+            fade_out: time(fade_out),
+        }),
+        ("fade", &[a1, a2, a3, t1, t2, t3, t4]) => Some(Tag::ComplexFade {
+            alphas: [alpha(a1), alpha(a2), alpha(a3)],
+            times: [time(t1), time(t2), time(t3), time(t4)],
+        }),
+        _ => None,
+    }
+}
+
+/// `\t([t1,t2,][accel,]tags)`, the tags start at the first backslash.
+fn transform(args: &str) -> Option<Tag> {
+    let (numbers, tags) = args.split_at(args.find('\\')?);
+    let numbers = match numbers.trim() {
+        "" => vec![],
+        numbers => numbers
+            .strip_suffix(',')?
+            .split(',')
+            .map(number)
+            .collect::<Option<Vec<f64>>>()?,
+    };
+    let (times, accel) = match numbers.as_slice() {
+        [] => (None, None),
+        &[accel] => (None, Some(accel)),
+        &[t1, t2] => (Some((t1 as i32, t2 as i32)), None),
+        &[t1, t2, accel] => (Some((t1 as i32, t2 as i32)), Some(accel)),
+        _ => return None,
+    };
+    let tags = override_block(tags)
+        .into_iter()
+        .filter_map(|item| match item {
+            OverrideItem::Tag(tag) => Some(tag),
+            OverrideItem::Comment(_) => None,
+        })
+        .collect();
+    Some(Tag::Transform { times, accel, tags })
+}
+
+/// `\clip(x1,y1,x2,y2)`, `\clip(drawing)` or `\clip(scale,drawing)`.
+fn clip(inverse: bool, args: &str) -> Option<Tag> {
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+    let shape = match args.as_slice() {
+        [x1, y1, x2, y2] => ClipShape::Rectangle {
+            x1: number(x1)?,
+            y1: number(y1)?,
+            x2: number(x2)?,
+            y2: number(y2)?,
+        },
+        [drawing] if !drawing.is_empty() => ClipShape::Vector {
+            scale: None,
+            drawing: Drawing::parse(drawing),
+        },
+        [scale, drawing] if !drawing.is_empty() => ClipShape::Vector {
+            scale: Some(scale.parse().ok()?),
+            drawing: Drawing::parse(drawing),
+        },
+        _ => return None,
+    };
+    Some(Tag::Clip { inverse, shape })
+}
+
+/// Reads a tag without parentheses, returning any text left after its argument.
+fn simple_tag(name: &str) -> Option<(Tag, &str)> {
+    let (tag, arg) = SIMPLE_TAGS
+        .iter()
+        .find_map(|tag| Some((*tag, name.strip_prefix(tag)?)))?;
+    let arg = arg.trim_start();
+    match tag {
+        "xbord" => optional(arg, float, Tag::BorderX),
+        "ybord" => optional(arg, float, Tag::BorderY),
+        "xshad" => optional(arg, float, Tag::ShadowX),
+        "yshad" => optional(arg, float, Tag::ShadowY),
+        "fax" => optional(arg, float, Tag::ShearX),
+        "fay" => optional(arg, float, Tag::ShearY),
+        "blur" => optional(arg, float, Tag::Blur),
+        "bord" => optional(arg, float, Tag::Border),
+        "frx" => optional(arg, float, Tag::RotationX),
+        "fry" => optional(arg, float, Tag::RotationY),
+        "frz" | "fr" => optional(arg, float, Tag::RotationZ),
+        "fscx" => optional(arg, float, Tag::ScaleX),
+        "fscy" => optional(arg, float, Tag::ScaleY),
+        "fsp" => optional(arg, float, Tag::Spacing),
+        "fs" => optional(arg, float, Tag::FontSize),
+        "fe" => optional(arg, int, Tag::FontEncoding),
+        "be" => optional(arg, float, Tag::BlurEdges),
+        "b" => optional(arg, int, Tag::Bold),
+        "i" => optional(arg, int, |i| Tag::Italic(i.map(|i| i != 0))),
+        "u" => optional(arg, int, |u| Tag::Underline(u.map(|u| u != 0))),
+        "s" => optional(arg, int, |s| Tag::StrikeOut(s.map(|s| s != 0))),
+        "shad" => optional(arg, float, Tag::Shadow),
+        "an" => optional(arg, int, |an| {
+            Tag::Alignment(an.and_then(Alignment::from_numpad))
+        }),
+        "a" => optional(arg, int, |a| {
+            Tag::LegacyAlignment(a.and_then(Alignment::from_legacy))
+        }),
+        "q" => optional(arg, int, |q| Tag::WrapStyle(q.and_then(wrap_style))),
+        "pbo" => optional(arg, float, Tag::BaselineOffset),
+        "p" => optional(arg, int, |p| {
+            Tag::DrawingMode(p.map_or(0, |p| p.max(0) as u32))
+        }),
+        "kf" | "K" => karaoke(arg, KaraokeEffect::Sweep),
+        "ko" => karaoke(arg, KaraokeEffect::Outline),
+        "k" => karaoke(arg, KaraokeEffect::Instant),
+        "alpha" => optional(arg, hex, |a| Tag::Alpha(a.map(|a| a as u8))),
+        "c" | "1c" | "2c" | "3c" | "4c" => {
+            let index = if tag == "c" {
+                1
+            } else {
+                tag.as_bytes()[0] - b'0'
+            };
+            optional(arg, hex, |c| {
+                Tag::Colour(index, c.map(|c| Colour::from_abgr(c & 0xFF_FFFF)))
+            })
+        }
+        "1a" | "2a" | "3a" | "4a" => {
+            let index = tag.as_bytes()[0] - b'0';
+            optional(arg, hex, |a| Tag::ColourAlpha(index, a.map(|a| a as u8)))
+        }
+        "fn" => Some((Tag::FontName(string(arg)), "")),
+        "r" => Some((Tag::Reset(string(arg)), "")),
+        _ => None,
+    }
+}
+
+/// An empty argument resets the value to the style's.
+fn optional<'a, T>(
+    arg: &'a str,
+    value: fn(&'a str) -> IResult<&'a str, T>,
+    tag: impl Fn(Option<T>) -> Tag,
+) -> Option<(Tag, &'a str)> {
+    if arg.trim().is_empty() {
+        return Some((tag(None), ""));
+    }
+    let (trailing, value) = value(arg).ok()?;
+    Some((tag(Some(value)), trailing))
+}
+
+fn karaoke(arg: &str, effect: KaraokeEffect) -> Option<(Tag, &str)> {
+    let (trailing, duration) = float(arg).ok()?;
+    Some((Tag::Karaoke(effect, duration), trailing))
+}
+
+fn string(arg: &str) -> Option<String> {
+    Some(arg.trim()).filter(|s| !s.is_empty()).map(String::from)
+}
+
+fn wrap_style(q: i32) -> Option<WrapStyle> {
+    match q {
+        0 => Some(WrapStyle::WrapStyle0),
+        1 => Some(WrapStyle::WrapStyle1),
+        2 => Some(WrapStyle::WrapStyle2),
+        3 => Some(WrapStyle::WrapStyle3),
+        _ => None,
+    }
+}
+
+/// A whole argument that must be a number.
+fn number(arg: &str) -> Option<f64> {
+    all_consuming(float)(arg.trim()).ok().map(|(_, n)| n)
+}
+
+/// A decimal number without exponent, such as `-1`, `2.` or `.5`.
+pub(super) fn float(input: &str) -> IResult<&str, f64> {
+    map_res(
+        recognize(pair(
+            opt(one_of("+-")),
+            alt((
+                recognize(pair(digit1, opt(pair(char('.'), digit0)))),
+                recognize(pair(char('.'), digit1)),
+            )),
+        )),
+        str::parse::<f64>,
+    )(input)
+}
+
+fn int(input: &str) -> IResult<&str, i32> {
+    map_res(
+        recognize(pair(opt(one_of("+-")), digit1)),
+        str::parse::<i32>,
+    )(input)
+}
+
+/// The `&HBBGGRR&` of colour and alpha tags, libass skipping any `&` and `H` before
+/// the digits.
+fn hex(input: &str) -> IResult<&str, u32> {
+    delimited(
+        take_while(|c| matches!(c, '&' | 'H' | 'h')),
+        map_res(take_while1(|c: char| c.is_ascii_hexdigit()), |h| {
+            u32::from_str_radix(h, 16)
+        }),
+        opt(char('&')),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_text;
+    use crate::prelude::{
+        Alignment, ClipShape, Colour, Drawing, KaraokeEffect, OverrideItem, Tag, TextSegment,
+    };
+
+    fn tags(segment: &TextSegment) -> Vec<Tag> {
+        match segment {
+            TextSegment::Override(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    OverrideItem::Tag(tag) => Some(tag.clone()),
+                    OverrideItem::Comment(_) => None,
+                })
+                .collect(),
+            _ => panic!("not an override block: {:?}", segment),
+        }
+    }
+
+    #[test]
+    fn test_parse_text_segments() {
+        let segments =
+            parse_text(r"{\an8\pos(960,50)}Hello\Nworld\h!{note}\n{\p1}m 0 0 l 10 0{\p0}end");
+        assert_eq!(
+            tags(&segments[0]),
+            [
+                Tag::Alignment(Some(Alignment::TopCenter)),
+                Tag::Position { x: 960.0, y: 50.0 }
+            ]
+        );
+        assert_eq!(
+            segments[1..],
+            [
+                TextSegment::Text("Hello".to_owned()),
+                TextSegment::HardBreak,
+                TextSegment::Text("world".to_owned()),
+                TextSegment::HardSpace,
+                TextSegment::Text("!".to_owned()),
+                TextSegment::Override(vec![OverrideItem::Comment("note".to_owned())]),
+                TextSegment::SoftBreak,
+                TextSegment::Override(vec![OverrideItem::Tag(Tag::DrawingMode(1))]),
+                TextSegment::Drawing(Drawing::parse("m 0 0 l 10 0")),
+                TextSegment::Override(vec![OverrideItem::Tag(Tag::DrawingMode(0))]),
+                TextSegment::Text("end".to_owned()),
+            ]
+        );
+        assert_eq!(
+            parse_text(r"a \ b {unclosed"),
+            [TextSegment::Text(r"a \ b {unclosed".to_owned())]
+        );
+        assert_eq!(
+            parse_text("C:\\\u{2060}New \\{note\\}"),
+            [TextSegment::Text("C:\\\u{2060}New {note}".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let segments = parse_text(
+            r"{\fscx120\fs\c&H00FF00&\3c&HFF&\alpha&H80&\2a&HFF&\kf50\K20\fnArial Bold\rAlt\b1 bold\t(0,500,0.5,\frz360\clip(0,0,10,10))\iclip(2,m 0 0 l 5 5)\fade(255,0,255,0,100,200,300)\move(1,2,3,4)\xyz(1)\bordx}",
+        );
+        assert_eq!(
+            tags(&segments[0]),
+            [
+                Tag::ScaleX(Some(120.0)),
+                Tag::FontSize(None),
+                Tag::Colour(1, Some(Colour::new(0, 0xFF, 0, 0))),
+                Tag::Colour(3, Some(Colour::new(0xFF, 0, 0, 0))),
+                Tag::Alpha(Some(0x80)),
+                Tag::ColourAlpha(2, Some(0xFF)),
+                Tag::Karaoke(KaraokeEffect::Sweep, 50.0),
+                Tag::Karaoke(KaraokeEffect::Sweep, 20.0),
+                Tag::FontName(Some("Arial Bold".to_owned())),
+                Tag::Reset(Some("Alt".to_owned())),
+                Tag::Bold(Some(1)),
+                Tag::Transform {
+                    times: Some((0, 500)),
+                    accel: Some(0.5),
+                    tags: vec![
+                        Tag::RotationZ(Some(360.0)),
+                        Tag::Clip {
+                            inverse: false,
+                            shape: ClipShape::Rectangle {
+                                x1: 0.0,
+                                y1: 0.0,
+                                x2: 10.0,
+                                y2: 10.0
+                            }
+                        }
+                    ],
+                },
+                Tag::Clip {
+                    inverse: true,
+                    shape: ClipShape::Vector {
+                        scale: Some(2),
+                        drawing: Drawing::parse("m 0 0 l 5 5")
+                    }
+                },
+                Tag::ComplexFade {
+                    alphas: [255, 0, 255],
+                    times: [0, 100, 200, 300]
+                },
+                Tag::Move {
+                    x1: 1.0,
+                    y1: 2.0,
+                    x2: 3.0,
+                    y2: 4.0,
+                    times: None
+                },
+                Tag::Unknown("xyz(1)".to_owned()),
+                Tag::Unknown("bordx".to_owned()),
+            ]
+        );
+        let TextSegment::Override(items) = &segments[0] else {
+            unreachable!()
+        };
+        assert!(items.contains(&OverrideItem::Comment(" bold".to_owned())));
+    }
+}
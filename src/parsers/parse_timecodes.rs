@@ -0,0 +1,122 @@
+use super::ParseError;
+use crate::prelude::Timecodes;
+use nom::{
+    bytes::complete::tag_no_case,
+    character::complete::{char, i64, space0, space1},
+    combinator::{all_consuming, opt},
+    number::complete::double,
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+
+/// `Assume <fps>`, the first line of a v1 file.
+fn assume(input: &str) -> IResult<&str, f64> {
+    all_consuming(preceded(tuple((tag_no_case("assume"), space1)), double))(input)
+}
+
+/// `<first frame>,<last frame>,<fps>`, a range of a v1 file.
+fn range(input: &str) -> IResult<&str, (i64, i64, f64)> {
+    let comma = || delimited(space0, char(','), space0);
+    all_consuming(tuple((
+        i64,
+        preceded(comma(), i64),
+        preceded(comma(), double),
+    )))(input)
+}
+
+/// `25`, `23.976` or `24000/1001`.
+fn rate(input: &str) -> IResult<&str, f64> {
+    let (rest, (numerator, denominator)) =
+        all_consuming(tuple((double, opt(preceded(char('/'), double)))))(input)?;
+    Ok((rest, numerator / denominator.unwrap_or(1.0)))
+}
+
+fn positive(fps: f64) -> bool {
+    fps.is_finite() && fps > 0.0
+}
+
+/// Parses an mkvmerge timecode (or timestamp) format v1 or v2 file, or a constant rate,
+/// as Aegisub does: blank lines and comments are skipped, v1 ranges must be in order and
+/// frames outside them use the assumed rate, and v2 times must not go back.
+pub(crate) fn parse_timecodes(input: &str) -> Result<Timecodes, ParseError> {
+    let mut lines = input
+        .strip_prefix('\u{feff}')
+        .unwrap_or(input)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let header = lines.next().unwrap_or(&input[input.len()..]);
+    let mut body = lines.filter(|line| !line.starts_with('#'));
+    let format = header.to_ascii_lowercase().replace("timestamp", "timecode");
+    if format.starts_with("# timecode format v1") {
+        v1(input, body)
+    } else if format.starts_with("# timecode format v2") {
+        v2(input, header, body)
+    } else if let Some(line) = body.next() {
+        Err(ParseError::unexpected_line(input, line))
+    } else {
+        match rate(header) {
+            Ok((_, fps)) if positive(fps) => Ok(Timecodes::constant(fps)),
+            _ => Err(ParseError::bad_header(input, header)),
+        }
+    }
+}
+
+fn v1<'a>(
+    input: &'a str,
+    mut lines: impl Iterator<Item = &'a str>,
+) -> Result<Timecodes, ParseError> {
+    let end = &input[input.len()..];
+    let assume_line = lines.next().unwrap_or(end);
+    let fps = match assume(assume_line) {
+        Ok((_, fps)) if positive(fps) => fps,
+        _ => return Err(ParseError::unexpected_line(input, assume_line)),
+    };
+
+    let mut timecodes = vec![];
+    let mut time: f64 = 0.0;
+    for line in lines {
+        let (_, (first, last, range_fps)) =
+            range(line).map_err(|_| ParseError::unexpected_line(input, line))?;
+        if first < timecodes.len() as i64 || last < first || !positive(range_fps) {
+            return Err(ParseError::invalid_timecodes(input, line));
+        }
+        while (timecodes.len() as i64) < first {
+            timecodes.push(time.round() as i64);
+            time += 1000.0 / fps;
+        }
+        while timecodes.len() as i64 <= last {
+            timecodes.push(time.round() as i64);
+            time += 1000.0 / range_fps;
+        }
+    }
+    // The end of the last range.
+    timecodes.push(time.round() as i64);
+    Ok(Timecodes::new(timecodes, time, fps))
+}
+
+fn v2<'a>(
+    input: &'a str,
+    header: &'a str,
+    lines: impl Iterator<Item = &'a str>,
+) -> Result<Timecodes, ParseError> {
+    let mut timecodes: Vec<i64> = vec![];
+    for line in lines {
+        let (_, time) =
+            all_consuming(double)(line).map_err(|_: nom::Err<nom::error::Error<&str>>| {
+                ParseError::invalid_number(input, line, "timecode")
+            })?;
+        let time = time.round() as i64;
+        if timecodes.last().is_some_and(|&previous| time < previous) {
+            return Err(ParseError::invalid_timecodes(input, line));
+        }
+        timecodes.push(time);
+    }
+    match (timecodes.first(), timecodes.last()) {
+        (Some(&first), Some(&last)) if first < last => {
+            let fps = (timecodes.len() - 1) as f64 * 1000.0 / (last - first) as f64;
+            Ok(Timecodes::new(timecodes, last as f64, fps))
+        }
+        _ => Err(ParseError::invalid_timecodes(input, header)),
+    }
+}
@@ -0,0 +1,100 @@
+use super::ParseError;
+use crate::prelude::Timestamp;
+use nom::{
+    character::complete::{char, digit1, one_of},
+    combinator::{all_consuming, opt},
+    multi::separated_list1,
+    sequence::{preceded, tuple},
+    IResult,
+};
+
+/// Splits `H:MM:SS.cc` into its digit groups, the fraction being optional.
+fn components(input: &str) -> IResult<&str, (&str, &str, &str, Option<&str>)> {
+    tuple((
+        digit1,
+        preceded(char(':'), digit1),
+        preceded(char(':'), digit1),
+        opt(preceded(char('.'), digit1)),
+    ))(input)
+}
+
+/// Parses a timestamp written exactly as `H:MM:SS.cc`, with minutes and seconds below 60.
+pub(crate) fn strict_timestamp(
+    origin: &str,
+    value: &str,
+    field: &str,
+) -> Result<Timestamp, ParseError> {
+    let invalid = || ParseError::invalid_timestamp(origin, value, field);
+    let (_, (h, m, s, cs)) = all_consuming(components)(value)
+        .map_err(|_: nom::Err<nom::error::Error<&str>>| invalid())?;
+    let cs = cs.ok_or_else(invalid)?;
+    if m.len() != 2 || s.len() != 2 || cs.len() != 2 {
+        return Err(invalid());
+    }
+    let (h, m, s, cs) = (number(h), number(m), number(s), number(cs));
+    match (h, m, s, cs) {
+        (Some(h), Some(m @ 0..=59), Some(s @ 0..=59), Some(cs)) => {
+            Timestamp::checked_new(h, m, s, cs).ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a timestamp as leniently as Aegisub does: any number of digits per group, an
+/// optional fraction of a second (`0:0:5.5` is five and a half seconds, where libass
+/// would read 5.05) and minutes or seconds past 59 carried over.
+pub(crate) fn lenient_timestamp(
+    origin: &str,
+    value: &str,
+    field: &str,
+) -> Result<Timestamp, ParseError> {
+    let invalid = || ParseError::invalid_timestamp(origin, value, field);
+    let (_, (h, m, s, fraction)) = all_consuming(components)(value.trim())
+        .map_err(|_: nom::Err<nom::error::Error<&str>>| invalid())?;
+    let seconds = number(h)
+        .and_then(|h| h.checked_mul(3600))
+        .zip(number(m).and_then(|m| m.checked_mul(60)))
+        .and_then(|(h, m)| h.checked_add(m))
+        .zip(number(s))
+        .and_then(|(hm, s)| hm.checked_add(s));
+    with_fraction(seconds, fraction).ok_or_else(invalid)
+}
+
+/// Parses the `HH:MM:SS,mmm` of SubRip or the `[HH:]MM:SS.mmm` of WebVTT, with any number
+/// of digits per group.
+pub(crate) fn clock_timestamp(
+    origin: &str,
+    value: &str,
+    field: &str,
+) -> Result<Timestamp, ParseError> {
+    let invalid = || ParseError::invalid_timestamp(origin, value, field);
+    let (_, (groups, fraction)) = all_consuming(tuple((
+        separated_list1(char(':'), digit1),
+        opt(preceded(one_of(",."), digit1)),
+    )))(value)
+    .map_err(|_: nom::Err<nom::error::Error<&str>>| invalid())?;
+    if !matches!(groups.len(), 2 | 3) {
+        return Err(invalid());
+    }
+    let seconds = groups.iter().try_fold(0u32, |seconds, group| {
+        seconds.checked_mul(60)?.checked_add(number(group)?)
+    });
+    with_fraction(seconds, fraction).ok_or_else(invalid)
+}
+
+/// Adds a fraction of a second to `seconds`. Only its first three digits matter, rounded
+/// to centiseconds.
+fn with_fraction(seconds: Option<u32>, fraction: Option<&str>) -> Option<Timestamp> {
+    let millis = fraction.map_or(Some(0), |f| {
+        let digits = &f[..f.len().min(3)];
+        number(digits).map(|n| n * 10u32.pow(3 - digits.len() as u32))
+    })?;
+    seconds?
+        .checked_mul(100)?
+        .checked_add((millis + 5) / 10)
+        .map(Timestamp::from_centis)
+}
+
+fn number(digits: &str) -> Option<u32> {
+    digits.parse::<u32>().ok()
+}
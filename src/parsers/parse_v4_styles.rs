@@ -1,113 +1,132 @@
-use super::parse_string1;
-use crate::prelude::{StyleEncoding, Styles};
+use super::{colour, key, number, parse_string1, split_fields, ParseError};
+use crate::prelude::{Alignment, BorderStyle, StyleEncoding, Styles};
 use nom::{
-    bytes::complete::tag,
     character::complete::multispace0,
     combinator::{map, opt},
     multi::many0,
     sequence::preceded,
     IResult,
 };
-/// Parses `Style:` lines
-pub(crate) fn parse_v4_styles(input: &str) -> IResult<&str, Vec<Styles>> {
-    map(many0(preceded(opt(multispace0), parse_style)), |fields| {
-        let mut style: Vec<Styles> = Vec::new();
-        for field in fields {
-            match field {
-                StyleField::Style(line) => style.push(parse_style_line(line)),
-            };
-        }
-        style
-    })(input)
+/// Parses `Style:` lines, mapping their values onto the columns of `format`. `legacy`
+/// styles, those of SSA v4, number their alignment the SSA way.
+pub(crate) fn parse_v4_styles<'a>(
+    origin: &str,
+    format: &[&str],
+    input: &'a str,
+    legacy: bool,
+) -> Result<(&'a str, Vec<Styles>), ParseError> {
+    let lines: IResult<&str, Vec<StyleField>> =
+        many0(preceded(opt(multispace0), parse_style))(input);
+    let (input, fields) = lines.map_err(|_| ParseError::unexpected_line(origin, input))?;
+    let mut style: Vec<Styles> = Vec::new();
+    for field in fields {
+        match field {
+            StyleField::Style(line) => style.push(parse_style_line(origin, format, line, legacy)?),
+        };
+    }
+    Ok((input, style))
 }
 
-fn parse_style(input: &str) -> IResult<&str, StyleField> {
-    map(preceded(tag("Style: "), parse_string1), |lss: &str| {
-        StyleField::Style(lss.to_string())
-    })(input)
+fn parse_style(input: &str) -> IResult<&str, StyleField<'_>> {
+    map(preceded(key("Style"), parse_string1), StyleField::Style)(input)
 }
 
-fn parse_style_line(style: String) -> Styles {
-    match style.split_once(',') {
-        Some((name, rest)) => {
-            let name = name;
-            let (font_name, rest) = rest.split_once(',').unwrap();
-            let (font_size, rest) = rest.split_once(',').unwrap();
-            let (primary_colour, rest) = rest.split_once(',').unwrap();
-            let (secondary_colour, rest) = rest.split_once(',').unwrap();
-            let (outline_colour, rest) = rest.split_once(',').unwrap();
-            let (back_colour, rest) = rest.split_once(',').unwrap();
-            let (bold, rest) = rest.split_once(',').unwrap();
-            let (italic, rest) = rest.split_once(',').unwrap();
-            let (underline, rest) = rest.split_once(',').unwrap();
-            let (strikeout, rest) = rest.split_once(',').unwrap();
-            let (scale_x, rest) = rest.split_once(',').unwrap();
-            let (scale_y, rest) = rest.split_once(',').unwrap();
-            let (spacing, rest) = rest.split_once(',').unwrap();
-            let (angle, rest) = rest.split_once(',').unwrap();
-            let (border_style, rest) = rest.split_once(',').unwrap();
-            let (outline, rest) = rest.split_once(',').unwrap();
-            let (shadow, rest) = rest.split_once(',').unwrap();
-            let (alignment, rest) = rest.split_once(',').unwrap();
-            let (margin_l, rest) = rest.split_once(',').unwrap();
-            let (margin_r, rest) = rest.split_once(',').unwrap();
-            let (margin_v, rest) = rest.split_once(',').unwrap();
-            let encoding = rest.parse::<i32>().expect("false parse");
-            let encoding = match encoding {
-                1 => StyleEncoding::Ansi,
-                2 => StyleEncoding::Default,
-                77 => StyleEncoding::Mac,
-                128 => StyleEncoding::ShiftJis,
-                129 => StyleEncoding::Hangeul,
-                130 => StyleEncoding::Johab,
-                134 => StyleEncoding::GB2312,
-                136 => StyleEncoding::ChineseBIG5,
-                161 => StyleEncoding::Greek,
-                162 => StyleEncoding::Turkish,
-                163 => StyleEncoding::Vietnamese,
-                177 => StyleEncoding::Hebrew,
-                178 => StyleEncoding::Arabic,
-                186 => StyleEncoding::Baltic,
-                204 => StyleEncoding::Russian,
-                222 => StyleEncoding::Thai,
-                238 => StyleEncoding::EastEuropean,
-                255 => StyleEncoding::Oem,
-                _ => StyleEncoding::Default,
-            };
-            Styles {
-                name: name.to_string(),
-                font_name: font_name.to_string(),
-                font_size: font_size.parse::<i32>().unwrap(),
-                primary_colour: primary_colour.to_string(),
-                secondary_colour: secondary_colour.to_string(),
-                outline_colour: outline_colour.to_string(),
-                back_colour: back_colour.to_string(),
-                bold: bold.parse::<i32>().unwrap(),
-                italic: italic.parse::<i32>().unwrap(),
-                underline: underline.parse::<i32>().unwrap(),
-                strikeout: strikeout.parse::<i32>().unwrap(),
-                scale_x: scale_x.parse::<i32>().unwrap(),
-                scale_y: scale_y.parse::<i32>().unwrap(),
-                spacing: spacing.parse::<i32>().unwrap(),
-                angle: angle.parse::<i32>().unwrap(),
-                border_style: border_style.parse::<i32>().unwrap(),
-                outline: outline.parse::<f32>().unwrap(),
-                shadow: shadow.parse::<i32>().unwrap(),
-                alignment: alignment.parse::<i32>().unwrap(),
-                margin_l: margin_l.parse::<f32>().unwrap(),
-                margin_r: margin_r.parse::<f32>().unwrap(),
-                margin_v: margin_v.parse::<f32>().unwrap(),
-                encoding,
+/// Columns missing from `format` keep their `Styles::default()` value,
+/// unknown columns end up in `Styles::extra_fields`. SSA's `TertiaryColour` is the
+/// outline colour and its `AlphaLevel`, which no renderer uses, is dropped.
+fn parse_style_line(
+    origin: &str,
+    format: &[&str],
+    style: &str,
+    legacy: bool,
+) -> Result<Styles, ParseError> {
+    let fields = split_fields(origin, style, format.len())?;
+    let mut styles = Styles::default();
+    for (&column, value) in format.iter().zip(fields) {
+        match column.to_ascii_lowercase().as_str() {
+            "name" => styles.name = value.to_string(),
+            "fontname" => styles.font_name = value.to_string(),
+            "fontsize" => styles.font_size = number(origin, value, column)?,
+            "primarycolour" => styles.primary_colour = colour(origin, value, column)?,
+            "secondarycolour" => styles.secondary_colour = colour(origin, value, column)?,
+            "outlinecolour" | "tertiarycolour" => {
+                styles.outline_colour = colour(origin, value, column)?
             }
+            "backcolour" => styles.back_colour = colour(origin, value, column)?,
+            "bold" => styles.bold = boolean(origin, value, column)?,
+            "italic" => styles.italic = boolean(origin, value, column)?,
+            "underline" => styles.underline = boolean(origin, value, column)?,
+            "strikeout" => styles.strikeout = boolean(origin, value, column)?,
+            "scalex" => styles.scale_x = number(origin, value, column)?,
+            "scaley" => styles.scale_y = number(origin, value, column)?,
+            "spacing" => styles.spacing = number(origin, value, column)?,
+            "angle" => styles.angle = number(origin, value, column)?,
+            "borderstyle" => styles.border_style = border_style(number(origin, value, column)?),
+            "outline" => styles.outline = number(origin, value, column)?,
+            "shadow" => styles.shadow = number(origin, value, column)?,
+            "alignment" => {
+                let alignment = number(origin, value, column)?;
+                styles.alignment = match legacy {
+                    true => Alignment::from_legacy(alignment),
+                    false => Alignment::from_numpad(alignment),
+                }
+                .ok_or_else(|| ParseError::invalid_number(origin, value, column))?
+            }
+            "alphalevel" if legacy => {}
+            "marginl" => styles.margin_l = number(origin, value, column)?,
+            "marginr" => styles.margin_r = number(origin, value, column)?,
+            "marginv" => styles.margin_v = number(origin, value, column)?,
+            "encoding" => styles.encoding = style_encoding(number(origin, value, column)?),
+            _ => styles
+                .extra_fields
+                .push((column.to_string(), value.to_string())),
         }
-        None => {
-            eprintln!("no fields were given");
-            Styles::default()
-        }
+    }
+    Ok(styles)
+}
+
+/// `-1` (or any other non-zero number) is true, `0` is false.
+fn boolean(origin: &str, value: &str, field: &str) -> Result<bool, ParseError> {
+    number::<i32>(origin, value, field).map(|b| b != 0)
+}
+
+/// Renderers draw anything but `3` and `4` as an outline; other numbers are kept so that
+/// writing the style back doesn't change it.
+fn border_style(border_style: i32) -> BorderStyle {
+    match border_style {
+        1 => BorderStyle::Outline,
+        3 => BorderStyle::OpaqueBox,
+        4 => BorderStyle::BackgroundBox,
+        other => BorderStyle::Other(other),
+    }
+}
+
+fn style_encoding(encoding: i32) -> StyleEncoding {
+    match encoding {
+        0 => StyleEncoding::Ansi,
+        1 => StyleEncoding::Default,
+        2 => StyleEncoding::Symbol,
+        77 => StyleEncoding::Mac,
+        128 => StyleEncoding::ShiftJis,
+        129 => StyleEncoding::Hangeul,
+        130 => StyleEncoding::Johab,
+        134 => StyleEncoding::GB2312,
+        136 => StyleEncoding::ChineseBIG5,
+        161 => StyleEncoding::Greek,
+        162 => StyleEncoding::Turkish,
+        163 => StyleEncoding::Vietnamese,
+        177 => StyleEncoding::Hebrew,
+        178 => StyleEncoding::Arabic,
+        186 => StyleEncoding::Baltic,
+        204 => StyleEncoding::Russian,
+        222 => StyleEncoding::Thai,
+        238 => StyleEncoding::EastEuropean,
+        255 => StyleEncoding::Oem,
+        other => StyleEncoding::Other(other),
     }
 }
 
 #[derive(Debug, PartialEq)]
-enum StyleField {
-    Style(String),
+enum StyleField<'a> {
+    Style(&'a str),
 }
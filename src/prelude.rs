@@ -1,9 +1,10 @@
 pub use crate::parsers::{
     parse_apg_section, parse_events_section, parse_file, parse_script_info_section,
-    parse_styles_section,
+    parse_ssa_styles_section, parse_styles_section, ParseError,
 };
 
 use crate::document;
+pub use document::document::ExtraSection;
 pub use document::document::SubtitlesFile;
 
 pub use document::document::ScriptInfo;
@@ -12,8 +13,25 @@ pub use document::document::YcbcrMatrix;
 
 pub use document::document::ProjectGarbage;
 
+pub use document::colour::Colour;
+pub use document::document::Alignment;
+pub use document::document::BorderStyle;
 pub use document::document::StyleEncoding;
 pub use document::document::Styles;
 
+pub use crate::formats::ConversionWarning;
 pub use document::document::Dialogue;
 pub use document::document::EventType;
+pub use document::drawing::{Drawing, DrawingCommand, Point};
+pub use document::keyframes::Keyframes;
+pub use document::resample::AspectMode;
+pub use document::resolved::{LineState, ResolvedStyle, RunState, StyledRun};
+pub use document::tags::ClipShape;
+pub use document::tags::KaraokeEffect;
+pub use document::tags::OverrideItem;
+pub use document::tags::Tag;
+pub use document::tags::TextSegment;
+pub use document::tags::{write_text, TextWriteOptions};
+pub use document::timecodes::{FrameTime, Timecodes};
+pub use document::timestamp::Timestamp;
+pub use document::timing::{EventFilter, KeyframeThresholds, TimingPostProcessor};
@@ -0,0 +1,80 @@
+use ass_parser::prelude::*;
+use std::fs::read_to_string;
+
+const FIXTURES: [&str; 3] = [
+    "my.ass",
+    "tests/fixtures/aegisub.ass",
+    "tests/fixtures/custom_format.ass",
+];
+
+/// The same document, with every combination of BOM and line ending the parser accepts.
+fn variants(input: &str) -> Vec<String> {
+    let bare = input.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut variants = Vec::new();
+    for eol in ["\n", "\r\n", "\r"] {
+        let text = bare.replace('\n', eol);
+        variants.push(format!("\u{feff}{}", text));
+        variants.push(text);
+    }
+    variants
+}
+
+#[test]
+fn parse_write_parse_is_stable() {
+    for fixture in FIXTURES {
+        let input = read_to_string(fixture).unwrap();
+        for variant in variants(&input) {
+            let parsed = parse_file(&variant).unwrap_or_else(|e| panic!("{}: {}", fixture, e));
+            let written = parsed.to_ass_string();
+            let reparsed = parse_file(&written).unwrap_or_else(|e| panic!("{}: {}", fixture, e));
+            assert_eq!(parsed, reparsed, "{}", fixture);
+            assert_eq!(written, reparsed.to_ass_string(), "{}", fixture);
+        }
+    }
+}
+
+/// `input` without its BOM, with `\n` line endings.
+fn normalized(input: &str) -> String {
+    input.trim_start_matches('\u{feff}').replace("\r\n", "\n")
+}
+
+/// Fixtures already in the layout of the writer come back as they were.
+/// `custom_format.ass` doesn't: its sections are reordered, its `Format:` lines get every
+/// known column and its `[Script Info]` the keys it leaves out.
+#[test]
+fn writes_fixtures_back() {
+    for fixture in ["my.ass", "tests/fixtures/aegisub.ass"] {
+        let input = read_to_string(fixture).unwrap();
+        let written = parse_file(&input).unwrap().to_ass_string();
+        assert_eq!(normalized(&written), normalized(&input), "{}", fixture);
+    }
+}
+
+#[test]
+fn keeps_script_info_key_order() {
+    let input = "[Script Info]\nPlayResY: 360\nTitle: Order\nLayoutResX: 640\nPlayResX: 640\n\
+        ScriptType: v4.00+\nWrapStyle: 0\nScaledBorderAndShadow: yes\nYCbCr Matrix: None\n";
+    let written = parse_file(input).unwrap().to_ass_string();
+    assert!(written.starts_with(&format!("\u{feff}{}\n[V4+ Styles]", input)));
+}
+
+#[test]
+fn keeps_style_lines() {
+    let input = read_to_string("tests/fixtures/aegisub.ass").unwrap();
+    let written = parse_file(&input).unwrap().to_ass_string();
+    for line in input.lines().filter(|line| line.starts_with("Style: ")) {
+        assert!(written.contains(line), "{}", line);
+    }
+}
+
+#[test]
+fn writes_known_layout() {
+    let parsed = parse_file(&read_to_string("my.ass").unwrap()).unwrap();
+    let written = parsed.to_ass_string();
+    assert!(written.starts_with("\u{feff}[Script Info]\nTitle: Translation File\n"));
+    assert!(written.contains("\n[Aegisub Project Garbage]\nLast Style Storage: Default\n"));
+    assert!(written.contains(
+        "Style: Default,Adobe Arabic,52,&H00FFFFFF,&H000000FF,&H00000000,&H80000001,-1,0,0,0,100,100,0,0,1,2.4,1,2,10,10,40,1\n"
+    ));
+    assert!(written.ends_with("Dialogue: 0,0:00:11.00,0:00:14.00,Default,,0,0,0,,\n"));
+}